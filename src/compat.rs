@@ -0,0 +1,208 @@
+//! Hardware revision compatibility.
+//!
+//! Firmware is built for one hardware revision, the fourth number of its
+//! version. Revisions that run the same firmware can be declared per module
+//! type in the rules file, as ranges of mutually compatible revisions:
+//!
+//! ```json
+//! {
+//!     "output-6ch": ["5-7"],
+//!     "input-6ch": ["2-3", "4"]
+//! }
+//! ```
+//!
+//! A release can also declare the module revisions it runs on with
+//! `compatible_revisions` (`"5-7"`) in its manifest; `check` and `import`
+//! record that in the file's sidecar.
+
+use std::{collections::HashMap, fmt, fs, io};
+
+use serde::Deserialize;
+
+use crate::{FirmwareVersion, ModuleType};
+
+/// An inclusive range of hardware revisions, written `5-7` or `5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct RevisionRange {
+    first: u8,
+    last: u8,
+}
+
+impl RevisionRange {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let number = |n: &str| {
+            n.trim()
+                .parse::<u8>()
+                .map_err(|_| format!("invalid revision range \"{text}\""))
+        };
+        let (first, last) = match text.split_once('-') {
+            Some((first, last)) => (number(first)?, number(last)?),
+            None => (number(text)?, number(text)?),
+        };
+        if first > last {
+            return Err(format!("invalid revision range \"{text}\""));
+        }
+        Ok(Self { first, last })
+    }
+
+    pub fn contains(&self, revision: u8) -> bool {
+        (self.first..=self.last).contains(&revision)
+    }
+}
+
+impl TryFrom<String> for RevisionRange {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        Self::parse(&text)
+    }
+}
+
+impl fmt::Display for RevisionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else {
+            write!(f, "{}-{}", self.first, self.last)
+        }
+    }
+}
+
+/// How a firmware file fits the hardware of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HardwareFit {
+    /// built for this hardware revision
+    Exact,
+    /// built for another revision that the rules or the release declare compatible
+    CrossRevision,
+    Incompatible,
+}
+
+impl HardwareFit {
+    pub fn fits(self) -> bool {
+        self != Self::Incompatible
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct CompatibilityRules(HashMap<ModuleType, Vec<RevisionRange>>);
+
+impl CompatibilityRules {
+    /// Read the rules file. A missing file means every revision needs its own
+    /// firmware, a file that doesn't parse is an error.
+    pub fn load(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| format!("{path}: {e}")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{path}: {e}")),
+        }
+    }
+
+    /// How firmware `firmware` fits a module reporting `module`. `declared` is the
+    /// revision range the release itself declares, if any.
+    pub fn fit(
+        &self,
+        firmware: &FirmwareVersion,
+        declared: Option<RevisionRange>,
+        module: &FirmwareVersion,
+    ) -> HardwareFit {
+        let (built_for, fitted) = (firmware.get_hardware(), module.get_hardware());
+        if built_for == fitted {
+            return HardwareFit::Exact;
+        }
+        if built_for[..3] != fitted[..3] {
+            return HardwareFit::Incompatible;
+        }
+        let by_rules = ModuleType::from_firmware(module)
+            .and_then(|t| self.0.get(&t))
+            .is_some_and(|ranges| {
+                ranges
+                    .iter()
+                    .any(|r| r.contains(built_for[3]) && r.contains(fitted[3]))
+            });
+        if by_rules || declared.is_some_and(|r| r.contains(fitted[3])) {
+            HardwareFit::CrossRevision
+        } else {
+            HardwareFit::Incompatible
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fw(version: &str) -> FirmwareVersion {
+        FirmwareVersion::from_filename(version.to_string()).unwrap()
+    }
+
+    fn rules(json: &str) -> CompatibilityRules {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn parses_revision_ranges() {
+        let range = RevisionRange::parse("5-7").unwrap();
+        assert!(!range.contains(4));
+        assert!(range.contains(5) && range.contains(7));
+        assert!(!range.contains(8));
+        assert_eq!(range.to_string(), "5-7");
+
+        let single = RevisionRange::parse("5").unwrap();
+        assert!(single.contains(5) && !single.contains(6));
+        assert_eq!(single.to_string(), "5");
+        assert_eq!(RevisionRange::parse(" 5 - 5 ").unwrap(), single);
+
+        for text in ["", "7-5", "5-", "-5", "a", "5-7-9", "256", "1-300"] {
+            assert!(RevisionRange::parse(text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn exact_and_incompatible_hardware() {
+        let rules = rules(r#"{ "output-6ch": ["5-7"] }"#);
+        let module = fw("20-20-2-6-0-0-1");
+        assert_eq!(rules.fit(&fw("20-20-2-6-1-0-0"), None, &module), HardwareFit::Exact);
+        // another module type never fits, whatever is declared
+        let declared = RevisionRange::parse("0-255").ok();
+        let other = fw("20-20-3-6-1-0-0");
+        assert_eq!(rules.fit(&other, declared, &module), HardwareFit::Incompatible);
+        assert!(!HardwareFit::Incompatible.fits());
+    }
+
+    #[test]
+    fn revisions_compatible_by_rules() {
+        let rules = rules(r#"{ "output-6ch": ["5-7"], "input-6ch": ["2-3", "4"] }"#);
+        let module = fw("20-20-2-6-0-0-1");
+        assert_eq!(rules.fit(&fw("20-20-2-5-1-0-0"), None, &module), HardwareFit::CrossRevision);
+        assert_eq!(rules.fit(&fw("20-20-2-8-1-0-0"), None, &module), HardwareFit::Incompatible);
+        // both revisions must be in the same range
+        let input = fw("20-10-1-3-0-0-1");
+        assert_eq!(rules.fit(&fw("20-10-1-2-1-0-0"), None, &input), HardwareFit::CrossRevision);
+        assert_eq!(rules.fit(&fw("20-10-1-4-1-0-0"), None, &input), HardwareFit::Incompatible);
+        // rules of one type don't apply to another
+        let bridge = fw("20-20-1-6-0-0-1");
+        assert_eq!(rules.fit(&fw("20-20-1-5-1-0-0"), None, &bridge), HardwareFit::Incompatible);
+    }
+
+    #[test]
+    fn revisions_declared_by_release() {
+        let rules = CompatibilityRules::default();
+        let module = fw("20-20-2-6-0-0-1");
+        let firmware = fw("20-20-2-5-1-0-0");
+        assert_eq!(rules.fit(&firmware, None, &module), HardwareFit::Incompatible);
+        let declared = RevisionRange::parse("5-6").ok();
+        assert_eq!(rules.fit(&firmware, declared, &module), HardwareFit::CrossRevision);
+        let declared = RevisionRange::parse("4-5").ok();
+        assert_eq!(rules.fit(&firmware, declared, &module), HardwareFit::Incompatible);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for json in [r#"{ "output-6ch": ["7-5"] }"#, r#"{ "foo": ["1"] }"#, r#"["1"]"#] {
+            assert!(serde_json::from_str::<CompatibilityRules>(json).is_err(), "{json}");
+        }
+    }
+}
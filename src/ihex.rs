@@ -0,0 +1,161 @@
+//! Intel HEX parsing.
+//!
+//! Intel HEX images are converted into the same S-record stream as .srec
+//! files, so the upload path only ever deals with `srec::Record`s.
+
+use crate::srec::Record;
+
+/// Parse and validate an Intel HEX file into S3 data records followed by an
+/// S7 record holding the start address.
+///
+/// Handles data (00), end of file (01), extended segment (02) and extended
+/// linear (04) address records, and the start segment (03) and start linear
+/// (05) address records. Every line is checked for its byte count and
+/// checksum, and the file must end with exactly one end-of-file record.
+pub fn parse(text: &str) -> Result<Vec<Record>, String> {
+    let mut records = Vec::new();
+    let mut base: u32 = 0;
+    let mut start: u32 = 0;
+    let mut finished = false;
+
+    for (i, raw) in text.split('\n').enumerate() {
+        let number = i + 1;
+        let line = raw.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if finished {
+            return Err(format!("line {number}: record after the end-of-file record"));
+        }
+        let hex_part = line
+            .strip_prefix(':')
+            .ok_or_else(|| format!("line {number}: does not start with ':'"))?;
+        let bytes =
+            hex::decode(hex_part).map_err(|_| format!("line {number}: invalid hex data"))?;
+        if bytes.len() < 5 {
+            return Err(format!("line {number}: record too short"));
+        }
+        let count = bytes[0] as usize;
+        if bytes.len() != count + 5 {
+            return Err(format!(
+                "line {number}: byte count says {count} data bytes but the line holds {}",
+                bytes.len() - 5
+            ));
+        }
+        if bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) != 0 {
+            return Err(format!("line {number}: checksum mismatch"));
+        }
+        let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let data = &bytes[4..4 + count];
+        let value = || data.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+        match (bytes[3], count) {
+            (0x00, _) => records.push(Record {
+                kind: 3,
+                address: base.wrapping_add(offset),
+                data: data.to_vec(),
+            }),
+            (0x01, 0) => finished = true,
+            (0x02, 2) => base = value() << 4,
+            (0x03, 4) => start = ((value() >> 16) << 4) + (value() & 0xFFFF),
+            (0x04, 2) => base = value() << 16,
+            (0x05, 4) => start = value(),
+            (kind @ 0x01..=0x05, _) => {
+                return Err(format!(
+                    "line {number}: record type {kind:02X} with {count} data bytes"
+                ))
+            }
+            (kind, _) => return Err(format!("line {number}: invalid record type {kind:02X}")),
+        }
+    }
+
+    if !finished {
+        return Err("no end-of-file record, file is truncated".into());
+    }
+    if records.is_empty() {
+        return Err("file contains no data records".into());
+    }
+    records.push(Record {
+        kind: 7,
+        address: start,
+        data: Vec::new(),
+    });
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(kind: u8, offset: u16, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8];
+        bytes.extend_from_slice(&offset.to_be_bytes());
+        bytes.push(kind);
+        bytes.extend_from_slice(data);
+        let sum = bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        bytes.push(sum.wrapping_neg());
+        format!(":{}", hex::encode_upper(bytes))
+    }
+
+    #[test]
+    fn parses_data_records() {
+        let text = ":10010000214601360121470136007EFE09D2190140\r\n:00000001FF\r\n";
+        let records = parse(text).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].kind, 3);
+        assert_eq!(records[0].address, 0x0100);
+        assert_eq!(records[0].data.len(), 16);
+        assert_eq!((records[1].kind, records[1].address), (7, 0));
+    }
+
+    #[test]
+    fn applies_extended_addresses() {
+        let text = [
+            line(0x04, 0, &[0x08, 0x00]),
+            line(0x00, 0x4000, &[1, 2]),
+            line(0x02, 0, &[0x12, 0x34]),
+            line(0x00, 0x0010, &[3]),
+            line(0x05, 0, &[0x08, 0x00, 0x41, 0x21]),
+            line(0x01, 0, &[]),
+        ]
+        .join("\n");
+        let records = parse(&text).unwrap();
+        let addresses: Vec<(u8, u32)> = records.iter().map(|r| (r.kind, r.address)).collect();
+        assert_eq!(
+            addresses,
+            [(3, 0x0800_4000), (3, 0x1234 * 16 + 0x10), (7, 0x0800_4121)]
+        );
+    }
+
+    #[test]
+    fn applies_start_segment_address() {
+        let text = [
+            line(0x00, 0, &[1]),
+            line(0x03, 0, &[0x12, 0x34, 0x00, 0x10]),
+            line(0x01, 0, &[]),
+        ]
+        .join("\n");
+        assert_eq!(parse(&text).unwrap()[1].address, 0x1234 * 16 + 0x10);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let data = line(0x00, 0, &[1, 2, 3]);
+        let end = line(0x01, 0, &[]);
+        let bad_checksum = format!("{}00", &data[..data.len() - 2]);
+        let cases = [
+            (format!("{bad_checksum}\n{end}"), "checksum mismatch"),
+            (format!("{}\n{end}", &data[..data.len() - 2]), "byte count"),
+            (format!("{}\n{end}", &data[..data.len() - 1]), "invalid hex"),
+            (data.clone(), "truncated"),
+            (format!("{data}\n{end}\n{data}"), "after the end-of-file"),
+            (format!("{}\n{end}", data.replacen(':', "", 1)), "does not start with ':'"),
+            (format!("{}\n{end}", line(0x06, 0, &[])), "invalid record type 06"),
+            (format!("{}\n{end}", line(0x04, 0, &[1])), "record type 04 with 1 data bytes"),
+            (end.clone(), "no data records"),
+        ];
+        for (text, expected) in cases {
+            let err = parse(&text).unwrap_err();
+            assert!(err.contains(expected), "{text:?}: {err}");
+        }
+    }
+}
@@ -1,13 +1,15 @@
 use std::{
+    collections::{BTreeMap, HashMap},
     env,
     fmt::{Display, Write},
     fs::{self, File},
-    io::{self, IsTerminal, Write as _},
+    io::{self, IsTerminal, Read, Write as _},
     mem,
+    path::{Path, PathBuf},
     process::{exit, Command},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        OnceLock,
     },
     time::Duration,
 };
@@ -29,6 +31,47 @@ static STARTED_FROM_CLI: AtomicBool = AtomicBool::new(false);
 /// existing config.
 static SCAN_HAD_ERRORS: AtomicBool = AtomicBool::new(false);
 
+/// Set by `--allow-unsigned` or `GO_MODULES_ALLOW_UNSIGNED=1` for development
+/// builds, and while `require_signatures` is off in the config: firmware and
+/// manifests without a valid signature from a trusted key are then accepted
+/// with a warning instead of refused.
+static ALLOW_UNSIGNED: AtomicBool = AtomicBool::new(false);
+
+/// Version pins from `PINS_PATH`, loaded once at startup. See `pins`.
+static PIN_POLICY: OnceLock<pins::PinPolicy> = OnceLock::new();
+
+fn pin_policy() -> &'static pins::PinPolicy {
+    PIN_POLICY.get_or_init(pins::PinPolicy::default)
+}
+
+/// Firmware sources from `CONFIG_PATH` in priority order, loaded once at startup. See `sources`.
+static FIRMWARE_SOURCES: OnceLock<Vec<FirmwareSource>> = OnceLock::new();
+
+fn firmware_sources() -> &'static [FirmwareSource] {
+    FIRMWARE_SOURCES.get_or_init(sources::default_sources)
+}
+
+/// The cloud URL and its fallback mirrors from `CONFIG_PATH`, `GO_MODULES_CLOUD_URL` or `--cloud-url`.
+static CLOUD_ENDPOINTS: OnceLock<sources::CloudEndpoints> = OnceLock::new();
+
+fn cloud_endpoints() -> &'static sources::CloudEndpoints {
+    CLOUD_ENDPOINTS.get_or_init(sources::CloudEndpoints::default)
+}
+
+/// Hardware revision compatibility rules from `COMPATIBILITY_PATH`, loaded once at startup. See `compat`.
+static COMPATIBILITY: OnceLock<compat::CompatibilityRules> = OnceLock::new();
+
+fn compatibility() -> &'static compat::CompatibilityRules {
+    COMPATIBILITY.get_or_init(compat::CompatibilityRules::default)
+}
+
+/// The parsed command line, set once at startup. See `CommandLine`.
+static COMMAND_LINE: OnceLock<CommandLine> = OnceLock::new();
+
+fn command_line() -> &'static CommandLine {
+    COMMAND_LINE.get_or_init(CommandLine::default)
+}
+
 fn flag_scan_error() {
     SCAN_HAD_ERRORS.store(true, Ordering::Relaxed);
 }
@@ -41,7 +84,7 @@ use crossterm::{
     terminal,
 };
 
-use futures::StreamExt;
+use futures::{future::join_all, stream, StreamExt};
 
 use spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
 
@@ -57,6 +100,19 @@ use serde_json::{json, Value};
 
 use sha2::{Digest, Sha256};
 
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+mod compat;
+mod ihex;
+mod pins;
+mod sources;
+mod srec;
+
+use compat::{HardwareFit, RevisionRange};
+use sources::FirmwareSource;
+
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn print_banner() {
@@ -170,9 +226,7 @@ fn run_select<T: Display>(prompt: &str, mut options: Vec<T>, mode: MenuMode) ->
         };
         match evt {
             Ok(Event::Key(KeyEvent { code: KeyCode::Up, .. })) => {
-                if selected > 0 {
-                    selected -= 1;
-                }
+                selected = selected.saturating_sub(1);
                 draw_menu(&options, selected, false, mode);
             }
             Ok(Event::Key(KeyEvent { code: KeyCode::Down, .. })) => {
@@ -288,10 +342,39 @@ const DUMMY_MESSAGE: [u8; 5] = [0; 5];
 const BOOTMESSAGE_LENGTH: usize = 46;
 const BOOTMESSAGE_LENGTH_CHECK: usize = 61;
 
+/// Largest S-record (byte count, address, data and checksum) that fits in a
+/// firmware frame: it is copied to `tx_buf[9..]` and must end before the
+/// frame checksum at `BOOTMESSAGE_LENGTH - 1`.
+const FRAME_RECORD_MAX: usize = BOOTMESSAGE_LENGTH - 1 - 9;
+
 const SLOT_PROMPT: &str = "Which slot to overwrite?";
 
 const FIRMWARE_DIR: &str = "/lib/firmware/gocontroll/";
+const MODULES_JSON_PATH: &str = "/lib/firmware/gocontroll/modules.json";
+/// Free space a download must leave on the firmware filesystem.
+const FIRMWARE_MIN_FREE_SPACE: u64 = 16 * 1024 * 1024;
+/// Copies of the per-module cloud manifests from the last `check`, used to
+/// verify firmware checksums without network access.
+const MANIFEST_CACHE_DIR: &str = "/lib/firmware/gocontroll/manifests/";
+/// Firmware files and module manifests `check` fetches at the same time.
+const PARALLEL_DOWNLOADS: usize = 4;
+/// Time to connect to a firmware server before trying the next endpoint.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// Time a manifest or signature request may take as a whole.
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Time a download may go without receiving data. Firmware downloads have no overall
+/// limit, a slow link is fine as long as it moves; an aborted one resumes on the next run.
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+/// The default cloud endpoint, see `sources::CloudEndpoints`.
 const CLOUD_BASE_URL: &str = "https://firmware.gocontroll.com";
+/// Ed25519 public keys, one hex encoded key per file, that firmware and manifest signatures are checked against.
+const TRUSTED_KEYS_DIR: &str = "/etc/go-modules/trusted-keys/";
+/// Firmware version pins for certified machine configurations, see `pins`.
+const PINS_PATH: &str = "/etc/go-modules/pins.json";
+/// go-modules settings, the firmware sources and whether signatures are required, see `sources`.
+const CONFIG_PATH: &str = "/etc/go-modules/config.json";
+/// Hardware revisions that run the same firmware, per module type, see `compat`.
+const COMPATIBILITY_PATH: &str = "/etc/go-modules/compatibility.json";
 
 const USAGE: &str = "Usage:
 go-modules <command> [subcommands]
@@ -301,19 +384,91 @@ go-modules
 commands:
 scan							Scan the modules in the controller
 update <all/slot#>				In case of all, try to update all modules, in case of a slot number, try to update that slot specifically
-overwrite <slot> <firmware>		Overwrite the firmware in <slot> with <firmware>
-check [--verbose/-v]			Fetch latest firmware for all modules from the GOcontroll cloud.
-								Downloads to /lib/firmware/gocontroll/ and validates checksums.
+overwrite <slot> <firmware>		Overwrite the firmware in <slot> with <firmware>, a file in /lib/firmware/gocontroll/,
+								a path to a file anywhere else, or a firmware version. The firmware must be built for the module's hardware
+								or a compatible revision of it.
+								Instead of a file, select from the firmware for the module's hardware with latest, previous
+								(newest older than installed), installed (reflash) or a software version like 1.2.3.
+	[--version <version>]		Firmware version of a file whose name, <file>.json sidecar or S0 header doesn't give it
+	[--base-address <address>]	Load address of a raw .bin image
+	[--allow-downgrade]			Allow flashing an older or the same version as installed, the TUI asks instead
+overwrite --type <type> <firmware>	Overwrite every module of a type (input-6ch, output-6ch, ...) or with an article number,
+overwrite --article <n> <firmware>	<firmware> as above. Shows the plan, then flashes all matching slots at once.
+rollback <all/slot#>			Re-flash the firmware a slot ran before its last update or overwrite
+check [--verbose/-v] [--all]	Fetch latest firmware for the fitted modules from the GOcontroll cloud and the configured sources.
+	[--scan]					Downloads to /lib/firmware/gocontroll/ (stored gzip compressed) and validates checksums.
+								The fitted modules are the ones in modules.json from the last scan, use --scan to scan them
+								first (stopping the services that use them) or --all to fetch the firmware for every module type.
 								Use --verbose or -v to show release dates and changelogs.
+								Downloads run in parallel, an interrupted download resumes where it stopped on the next check.
+releases <slot/hardware/type>	List every release the remote sources offer for a module with dates and changelogs, marking
+								the versions in the library and installed. A slot number, a hardware version or a module type.
+download <hardware> <version>	Download one specific release, e.g. an older version, into /lib/firmware/gocontroll/ with the
+								same checks as check. <hardware> is a slot number or a hardware version like 20-10-1-5.
+verify <all/file/dir>			Validate firmware files without touching the modules, all checks /lib/firmware/gocontroll/.
+								Exits non-zero when any file is bad.
+import <dir/archive>			Install a firmware bundle (firmware files plus module manifests, a directory or .tar/.tar.gz)
+								after checking every SHA256, nothing is installed when a file fails the checks. When writing
+								a file fails, the files this import added before it are removed again.
+export <dir/archive>			Write the firmware library as a bundle for import on another controller
+firmware list					List the firmware files per hardware, marking the newest and the installed ones
+firmware prune --keep <n>		Delete all but the <n> newest versions per hardware, installed and rollback versions are always kept, as well as interrupted downloads
+firmware compress				Store the uncompressed firmware files gzip compressed (<file>.gz), like check and import do
+firmware info <file>				Show a firmware file's source, release date, changelog, checksum and download time,
+								<file> with or without the .gz of a compressed file
+firmware remove <file> [--force]	Delete a firmware file, --force is needed when its version is installed
+
+options:
+--allow-unsigned				Accept firmware and manifests without a valid signature (development builds only),
+								also set by GO_MODULES_ALLOW_UNSIGNED=1. Signatures are <file>.sig next to the file,
+								checked against the keys in /etc/go-modules/trusted-keys/. They are only required
+								with {\"require_signatures\": true} in /etc/go-modules/config.json, until then
+								unsigned content is accepted with a warning.
+--cloud-url <url>[,<mirror>...]	Use this cloud endpoint and fallback mirrors instead of the configured ones,
+								also set by GO_MODULES_CLOUD_URL.
+
+version pins:
+/etc/go-modules/pins.json pins slots, article numbers or module types to a version or constraint,
+for example {\"slots\": {\"3\": \"1.4.2\"}, \"module_types\": {\"output-6ch\": \">=1.4, <2\"}}.
+update, the scan Update column and check only pick versions the pins allow.
+
+firmware sources:
+/etc/go-modules/config.json lists where firmware comes from, in priority order, for example
+{\"sources\": [{\"type\": \"local-dir\", \"path\": \"/opt/firmware\"}, {\"type\": \"http-mirror\", \"url\": \"http://mirror\"}, {\"type\": \"cloud\"}]}.
+Local directories are read in place, check downloads from mirrors and the cloud into /lib/firmware/gocontroll/.
+The cloud endpoint and its fallback mirrors, tried in order, are set with
+{\"cloud\": {\"url\": \"https://firmware.gocontroll.com\", \"mirrors\": [\"https://backup\"]}} in the same file,
+or replaced with a comma separated list in GO_MODULES_CLOUD_URL or --cloud-url <url>[,<mirror>...].
+
+hardware compatibility:
+/etc/go-modules/compatibility.json lists per module type the hardware revisions that run the same firmware,
+for example {\"output-6ch\": [\"5-7\"]}. A release can also declare \"compatible_revisions\": \"5-7\" in its manifest.
+update and overwrite then offer firmware built for another revision in a range, marked as cross-revision.
 
 examples:
 go-modules										Use with the tui (recommended)
 go-modules scan									Scan all modules in the controller
 go-modules update all							Try to update all modules in the controller
 go-modules update 1								Try to update the module in slot 1
-go-modules overwrite 1 20-10-1-5-0-0-9.srec		Forcefully overwrite the module in slot 1 with 20-10-1-5-0-0-9.srec (can be used to downgrade modules)
+go-modules overwrite 1 20-10-1-5-0-0-9.srec		Forcefully overwrite the module in slot 1 with 20-10-1-5-0-0-9.srec
+go-modules overwrite 1 20-10-1-5-0-0-7.srec --allow-downgrade	Put an older version on the module in slot 1
+go-modules overwrite 1 latest					Overwrite slot 1 with the newest firmware for its hardware
+go-modules overwrite 2 0.0.7 --allow-downgrade	Put software version 0.0.7 on the module in slot 2
+go-modules overwrite --type output-6ch 1.2.0		Put software version 1.2.0 on every 6 channel output module
+go-modules overwrite 1 build.bin --version 20-10-1-5-0-0-9 --base-address 0x8000	Overwrite slot 1 with a raw binary from /lib/firmware/gocontroll/
+go-modules overwrite 1 /tmp/app.srec			Overwrite slot 1 with a freshly copied build, version from its name or S0 header
+go-modules rollback 1							Put the previous firmware back on the module in slot 1
 go-modules check								Fetch latest firmware files from the GOcontroll cloud
-go-modules check --verbose						Fetch latest firmware files and show release dates and changelogs";
+go-modules check --verbose						Fetch latest firmware files and show release dates and changelogs
+go-modules check --all							Fetch the latest firmware for every module type, e.g. to export a complete library
+go-modules check --scan							Scan the modules, then fetch the latest firmware for them
+go-modules check --cloud-url http://localhost:8000	Run check against a local copy of the cloud's manifest tree
+go-modules releases 3							Show the full release history of the module in slot 3
+go-modules download 20-10-1-5 0.0.7				Download version 0.0.7 for 6 channel input modules, hardware revision 5
+go-modules verify /media/usb/firmware			Check the firmware files on a USB stick before installing them
+go-modules firmware prune --keep 2				Keep only the two newest firmware versions of every module type
+go-modules export /media/usb/bundle.tar.gz		Put the firmware library on a USB stick
+go-modules import /media/usb/bundle.tar.gz		Install the firmware from a USB stick on a controller without internet";
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 struct FirmwareVersion {
@@ -321,24 +476,41 @@ struct FirmwareVersion {
 }
 
 impl FirmwareVersion {
-    /// create a FirmwareVersion from a filename for example 20-10-1-5-0-0-9.srec
+    /// create a FirmwareVersion from a version like 20-10-1-5-0-0-9, optionally followed by file
+    /// extensions as in 20-10-1-5-0-0-9.srec or 20-10-1-5-0-0-9.srec.gz.
+    /// Exactly seven decimal numbers are required, a partial version is not padded with zeros.
     fn from_filename(name: String) -> Option<Self> {
         let mut firmware: [u8; 7] = [0u8; 7];
-        if let Some(no_extension) = name.split('.').next() {
-            let numbers = no_extension.split('-');
-
-            for (i, num) in numbers.enumerate() {
-                let part = firmware.get_mut(i)?;
-                if let Ok(file_part) = num.parse::<u8>() {
-                    *part = file_part;
-                } else {
-                    return None;
-                }
+        let no_extension = name.split('.').next()?;
+        let mut numbers = no_extension.split('-');
+        for part in firmware.iter_mut() {
+            let num = numbers.next()?;
+            if num.is_empty() || !num.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
             }
+            *part = num.parse().ok()?;
+        }
+        if numbers.next().is_some() {
+            return None;
         }
         Some(Self { firmware })
     }
 
+    /// find a firmware version like 20-10-1-5-0-0-9 in S0 header text, for example
+    /// the output filename objcopy puts there. All seven numbers must be present.
+    fn from_header(header: &str) -> Option<Self> {
+        header
+            .split(|c: char| !(c.is_ascii_digit() || c == '-'))
+            .filter(|token| token.split('-').count() == 7)
+            .find_map(|token| {
+                let mut firmware = [0u8; 7];
+                for (part, num) in firmware.iter_mut().zip(token.split('-')) {
+                    *part = num.parse().ok()?;
+                }
+                Some(Self { firmware })
+            })
+    }
+
     /// get the software part of the firmware version
     fn get_software(&self) -> &[u8] {
         self.firmware.get(4..7).unwrap()
@@ -367,6 +539,14 @@ impl FirmwareVersion {
     fn as_filename(&self) -> String {
         format!("{}.srec", self.as_string())
     }
+
+    /// the version a module reporting `module` runs after this firmware is flashed onto it:
+    /// its own hardware bytes, which differ for firmware from a compatible revision, and this software
+    fn flashed_onto(&self, module: &FirmwareVersion) -> Self {
+        let mut firmware = module.firmware;
+        firmware[4..].copy_from_slice(self.get_software());
+        Self { firmware }
+    }
 }
 
 impl Display for FirmwareVersion {
@@ -375,11 +555,225 @@ impl Display for FirmwareVersion {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum FirmwareFormat {
+    Srec,
+    IntelHex,
+    /// raw image, needs a base address from the sidecar or the command line
+    Binary,
+}
+
+impl FirmwareFormat {
+    /// The format of a firmware file by its extension, looking through a `.gz`.
+    fn from_path(path: &Path) -> Option<Self> {
+        let path = if is_compressed(path) {
+            Path::new(path.file_stem()?)
+        } else {
+            path
+        };
+        match path.extension()?.to_str()? {
+            "srec" => Some(Self::Srec),
+            "hex" => Some(Self::IntelHex),
+            "bin" => Some(Self::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// Optional `<file>.json` next to a firmware file, for files whose name does not
+/// encode the version, or raw binaries that need a load address:
+/// `{"firmware": "20-10-1-5-0-0-9", "base_address": "0x08004000"}`
+///
+/// `check` and `import` also record the release metadata here, so it can be shown
+/// without a connection to the cloud.
+#[derive(Deserialize, Serialize, Default)]
+struct FirmwareSidecar {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    firmware: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base_address: Option<String>,
+    /// firmware source the file was downloaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// release date from the manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    changelog: Option<String>,
+    /// SHA256 of the file from the manifest, hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    /// when the file was downloaded or imported, UTC
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    downloaded: Option<String>,
+    /// module hardware revisions the file runs on from the manifest, like `5-7`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compatible_revisions: Option<String>,
+}
+
+/// `<file>.json` next to a firmware file
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".json");
+    PathBuf::from(sidecar)
+}
+
+/// The sidecar of a firmware file, the default when there is none.
+fn read_sidecar(path: &Path) -> Result<FirmwareSidecar, String> {
+    match fs::read_to_string(sidecar_path(path)) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("invalid metadata file: {e}")),
+        Err(_) => Ok(FirmwareSidecar::default()),
+    }
+}
+
+/// A firmware file: the version it holds and where and how to load it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct FirmwareFile {
+    version: FirmwareVersion,
+    path: PathBuf,
+    format: FirmwareFormat,
+    /// load address of a raw binary image
+    base_address: Option<u32>,
+    /// firmware source the file came from, empty for files put in place by hand
+    origin: String,
+    /// release date recorded in the sidecar
+    released: Option<String>,
+    /// module hardware revisions the release declares it runs on, from the sidecar
+    compatible_revisions: Option<RevisionRange>,
+}
+
+impl FirmwareFile {
+    /// Describe the firmware file at `path`. The version comes from `version` when given, else from
+    /// the sidecar, else from the filename, else from the S0 header of an S-record file.
+    /// Binaries need a base address from `base_address` or the sidecar.
+    fn from_path(
+        path: &Path,
+        version: Option<FirmwareVersion>,
+        base_address: Option<u32>,
+    ) -> Result<Self, String> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let format = FirmwareFormat::from_path(path)
+            .ok_or_else(|| format!("{name}: unsupported format, use .srec, .hex or .bin"))?;
+        let sidecar = read_sidecar(path).map_err(|e| format!("{name}: {e}"))?;
+        let version = match (version, &sidecar.firmware) {
+            (Some(v), _) => v,
+            (None, Some(fw)) => FirmwareVersion::from_filename(fw.clone())
+                .ok_or_else(|| format!("{name}: invalid firmware version {fw} in metadata file"))?,
+            (None, None) => FirmwareVersion::from_filename(name.clone())
+                .or_else(|| match format {
+                    FirmwareFormat::Srec => srec::header_text(&read_file_start(path))
+                        .and_then(|header| FirmwareVersion::from_header(&header)),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    format!("{name}: no firmware version in the filename, S0 header or metadata file")
+                })?,
+        };
+        let base_address = match (base_address, &sidecar.base_address) {
+            (Some(b), _) => Some(b),
+            (None, Some(b)) => Some(
+                parse_address(b)
+                    .ok_or_else(|| format!("{name}: invalid base address {b} in metadata file"))?,
+            ),
+            (None, None) => None,
+        };
+        if format == FirmwareFormat::Binary && base_address.is_none() {
+            return Err(format!("{name}: raw binary needs a base address"));
+        }
+        let compatible_revisions = sidecar
+            .compatible_revisions
+            .as_deref()
+            .map(RevisionRange::parse)
+            .transpose()
+            .map_err(|e| format!("{name}: {e} in metadata file"))?;
+        Ok(Self {
+            version,
+            path: path.to_path_buf(),
+            format,
+            base_address,
+            origin: sidecar.source.unwrap_or_default(),
+            released: sidecar.date.filter(|d| !d.is_empty()),
+            compatible_revisions,
+        })
+    }
+
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// whether `name` names this file, with or without the .gz of a compressed file
+    fn is_named(&self, name: &str) -> bool {
+        uncompressed_name(&self.file_name()) == uncompressed_name(name)
+    }
+
+    /// How this file fits a module reporting firmware `module`, see `compat`.
+    fn fit(&self, module: &FirmwareVersion) -> HardwareFit {
+        compatibility().fit(&self.version, self.compatible_revisions, module)
+    }
+
+    /// `" (cross-revision, built for HW rev N)"` when this file fits a module reporting
+    /// `module` only through a compatibility rule, empty otherwise.
+    fn cross_revision_note(&self, module: &FirmwareVersion) -> String {
+        match self.fit(module) {
+            HardwareFit::CrossRevision => format!(
+                " (cross-revision, built for HW rev {})",
+                self.version.get_hardware()[3]
+            ),
+            _ => String::new(),
+        }
+    }
+
+    /// Whether this file holds the firmware a module reporting `fw` runs: the same
+    /// software, built for its hardware revision or a compatible one.
+    fn provides(&self, fw: &FirmwareVersion) -> bool {
+        self.version.get_software() == fw.get_software() && self.fit(fw).fits()
+    }
+}
+
+impl Display for FirmwareFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.format == FirmwareFormat::Srec
+            && uncompressed_name(&self.file_name()) == self.version.as_filename()
+        {
+            write!(f, "{}", self.version)?;
+        } else {
+            write!(f, "{} ({})", self.version.as_string(), self.file_name())?;
+        }
+        let mut notes = Vec::new();
+        if !self.origin.is_empty() {
+            notes.push(self.origin.clone());
+        }
+        if let Some(date) = &self.released {
+            notes.push(format!("released {date}"));
+        }
+        if !notes.is_empty() {
+            write!(f, " [{}]", notes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// parse a decimal or 0x prefixed hexadecimal address
+fn parse_address(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
 enum CommandArg {
     Scan,
     Update,
     Overwrite,
     Check,
+    Verify,
+    Rollback,
 }
 
 //impl display to make sure we don't have capital letters, as the don't match the commands
@@ -393,6 +787,8 @@ impl Display for CommandArg {
                 Self::Update => "update",
                 Self::Overwrite => "overwrite",
                 Self::Check => "check",
+                Self::Verify => "verify",
+                Self::Rollback => "rollback",
             }
         )
     }
@@ -416,7 +812,7 @@ const MODULES_JSON_SCHEMA_VERSION: &str = "1.0.0";
 
 /// Module type identifiers from configuration.md §4. Mapped from the first
 /// 3 firmware bytes — see `ModuleType::from_firmware`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 enum ModuleType {
     #[serde(rename = "input-6ch")]
     Input6Ch,
@@ -610,6 +1006,10 @@ struct SlotEntry {
     firmware_version: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     label: Option<String>,
+    /// Firmware the slot ran before the last flash, target of `rollback`.
+    /// Cleared when a different module type or hardware revision is fitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_firmware: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     module: Option<Value>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -632,6 +1032,7 @@ impl SlotEntry {
             hardware_version: None,
             firmware_version: None,
             label: None,
+            previous_firmware: None,
             module: None,
             channels: Vec::new(),
         }
@@ -661,6 +1062,12 @@ fn hardware_version_string(fw: &FirmwareVersion) -> String {
     format!("1.{:02}", hw[3])
 }
 
+/// The four hardware bytes as written in firmware names, e.g. `20-10-1-5`.
+fn hardware_string(fw: &FirmwareVersion) -> String {
+    let hw = fw.get_hardware();
+    format!("{}-{}-{}-{}", hw[0], hw[1], hw[2], hw[3])
+}
+
 /// SemVer firmware version from bytes 4,5,6.
 fn firmware_version_string(fw: &FirmwareVersion) -> String {
     let sw = fw.get_software();
@@ -687,22 +1094,51 @@ struct CloudMainManifest {
 #[derive(Deserialize)]
 struct CloudModuleEntry {
     manifest: String,
+    /// lets `check` skip the manifests for modules that are not fitted without fetching them
+    #[serde(default)]
+    hardware_version: Option<String>,
 }
 
-#[derive(Deserialize)]
+/// Also the manifest format of offline bundles, see `import_bundle`.
+#[derive(Deserialize, Serialize)]
 struct CloudModuleManifest {
     name: String,
     hardware_version: String,
     releases: Vec<CloudRelease>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone)]
 struct CloudRelease {
     sw_version: String,
     file: String,
     date: String,
     sha256: String,
     changelog: String,
+    /// module hardware revisions the release runs on, like `5-7`, see `compat`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compatible_revisions: Option<String>,
+}
+
+impl CloudRelease {
+    /// the filename of the release file, the last part of its path, None when that is not
+    /// a firmware file name, see `check_firmware_name`
+    fn file_name(&self) -> Option<&str> {
+        self.file
+            .split('/')
+            .next_back()
+            .filter(|f| check_firmware_name(f).is_ok())
+    }
+
+    /// the firmware version in the filename
+    fn version(&self) -> Option<FirmwareVersion> {
+        FirmwareVersion::from_filename(self.file_name()?.to_string())
+    }
+
+    fn declared_revisions(&self) -> Option<RevisionRange> {
+        self.compatible_revisions
+            .as_deref()
+            .and_then(|r| RevisionRange::parse(r).ok())
+    }
 }
 
 impl Module {
@@ -966,9 +1402,10 @@ impl Module {
         tx_buf[BOOTMESSAGE_LENGTH - 1] = calculate_checksum(&tx_buf, BOOTMESSAGE_LENGTH - 1);
 
         //this is super scuffed but for some reason it queues up events, so when in earlier parts the interrupt happens it fills the queue, causing it to skip the memory wipe interrupt and fail
-        while let Ok(_) = timeout(Duration::from_millis(1), self.interrupt.next()).await {
-            ()
-        }
+        while timeout(Duration::from_millis(1), self.interrupt.next())
+            .await
+            .is_ok()
+        {}
 
         //register the interrupt waiter
         let interrupt = self.interrupt.next();
@@ -1037,7 +1474,7 @@ impl Module {
     ///```
     async fn overwrite_module(
         &mut self,
-        new_firmware: &FirmwareVersion,
+        new_firmware: &FirmwareFile,
         multi_progress: MultiProgress,
         style: ProgressStyle,
     ) -> Result<(), UploadError> {
@@ -1047,44 +1484,47 @@ impl Module {
         let mut tx_buf = [0u8; BOOTMESSAGE_LENGTH + 1];
         let mut rx_buf = [0u8; BOOTMESSAGE_LENGTH + 1];
 
-        //open and read the firmware file
-        let firmware_content_string = match fs::read_to_string(format!(
-            "{}{}",
-            FIRMWARE_DIR,
-            new_firmware.as_filename()
-        )) {
-            Ok(file) => file,
+        //read, authenticate, parse and validate the whole file before anything is sent, a bad file must not get past the wipe
+        let records = match read_firmware(&new_firmware.path)
+            .map_err(|e| format!("could not read {}: {e}", new_firmware.file_name()))
+            .and_then(|data| {
+                check_recorded_checksum(new_firmware, &data)?;
+                if let Err(err) = verify_signature(&data, &signature_path(&new_firmware.path)) {
+                    if !ALLOW_UNSIGNED.load(Ordering::Relaxed) {
+                        return Err(format!("{err}, use --allow-unsigned for development builds"));
+                    }
+                    eprintln!("Warning: flashing {} anyway: {err}", new_firmware.file_name());
+                }
+                decode_firmware(new_firmware, &data)
+            }) {
+            Ok(records) => records,
             Err(err) => {
                 eprintln!(
-                    "Error: could not read {}\n{}",
-                    new_firmware.as_filename(),
+                    "Error: {} is not valid firmware, slot {} left untouched\n{}",
+                    new_firmware.file_name(),
+                    self.slot,
                     err
                 );
                 return Err(UploadError::FirmwareUntouched(self.slot));
             }
         };
+        let frames: Vec<Vec<u8>> = records.iter().map(srec::Record::to_bytes).collect();
 
-        //upload
-        let lines: Vec<&str> = firmware_content_string.split('\n').collect();
-
-        if lines.len() <= 1 {
-            eprintln!("Error: firmware file corrupt");
-            return Err(UploadError::FirmwareUntouched(self.slot));
-        }
         //wipe the old firmware and set the new software version no err_n_restart_services from this point on, errors lead to corrupt firmware.
         tx_buf[0] = 29;
         tx_buf[1] = (BOOTMESSAGE_LENGTH - 1) as u8;
         tx_buf[2] = 29;
-        let sw = new_firmware.get_software();
+        let sw = new_firmware.version.get_software();
         tx_buf[6] = sw[0];
         tx_buf[7] = sw[1];
         tx_buf[8] = sw[2];
         tx_buf[BOOTMESSAGE_LENGTH - 1] = calculate_checksum(&tx_buf, BOOTMESSAGE_LENGTH - 1);
 
         //this is super scuffed but for some reason it queues up events, so when in earlier parts the interrupt happens it fills the queue, causing it to skip the memory wipe interrupt and fail
-        while let Ok(_) = timeout(Duration::from_millis(1), self.interrupt.next()).await {
-            ()
-        }
+        while timeout(Duration::from_millis(1), self.interrupt.next())
+            .await
+            .is_ok()
+        {}
 
         //register the interrupt waiter
         let interrupt = self.interrupt.next();
@@ -1103,29 +1543,23 @@ impl Module {
         _ = timeout(Duration::from_millis(3500), interrupt).await;
         spinner.finish_and_clear();
 
-        let progress = multi_progress.add(ProgressBar::new(lines.len() as u64));
+        let progress = multi_progress.add(ProgressBar::new(frames.len() as u64));
         progress.set_style(style);
         progress.set_message(format!(
             "Uploading firmware {} to slot {}",
-            new_firmware.as_string(),
+            new_firmware.version.as_string(),
             self.slot
         ));
 
         let mut line_number: usize = 0;
-        #[allow(unused_assignments)]
-        let mut send_buffer_pointer: usize = 0;
-        #[allow(unused_assignments)]
-        let mut message_pointer: usize = 0;
         let mut message_type: u8 = 0;
         let mut firmware_line_check: usize = usize::MAX; //set line check to usize::MAX for the first message so we know its the first message
         let mut firmware_error_counter: u8 = 0;
 
         while message_type != 7 {
             // 7 marks the last line of the .srec file
-            message_type = u8::from_str_radix(lines[line_number].get(1..2).unwrap(), 16).unwrap();
+            message_type = records[line_number].kind;
 
-            let line_length =
-                u8::from_str_radix(lines[line_number].get(2..4).unwrap(), 16).unwrap();
             //first time the last line is reached, it is not allowed to send the last line, as it could cause the module to jump to the firmware, potentially leaving line n-1 with an error
             if message_type == 7 && firmware_line_check != line_number {
                 //prepare dummy message to get feedback from the previous message
@@ -1170,33 +1604,12 @@ impl Module {
             tx_buf[1] = (BOOTMESSAGE_LENGTH - 1) as u8;
             tx_buf[2] = 39;
 
-            send_buffer_pointer = 6;
-            tx_buf[send_buffer_pointer] = (line_number >> 8) as u8;
-            send_buffer_pointer += 1;
-            tx_buf[send_buffer_pointer] = line_number as u8;
-            send_buffer_pointer += 1;
-            tx_buf[send_buffer_pointer] = message_type;
-            send_buffer_pointer += 1;
-
-            message_pointer = 2;
-            while message_pointer < ((line_length * 2) + 2) as usize {
-                tx_buf[send_buffer_pointer] = u8::from_str_radix(
-                    lines[line_number]
-                        .get(message_pointer..message_pointer + 2)
-                        .unwrap(),
-                    16,
-                )
-                .unwrap();
-                send_buffer_pointer += 1;
-                message_pointer += 2;
-            }
-            tx_buf[send_buffer_pointer] = u8::from_str_radix(
-                lines[line_number]
-                    .get(message_pointer..message_pointer + 2)
-                    .unwrap(),
-                16,
-            )
-            .unwrap();
+            tx_buf[6] = (line_number >> 8) as u8;
+            tx_buf[7] = line_number as u8;
+            tx_buf[8] = message_type;
+            // byte count, address, data and checksum of the record, firmware_records made sure it fits
+            let frame = &frames[line_number];
+            tx_buf[9..9 + frame.len()].copy_from_slice(frame);
 
             tx_buf[BOOTMESSAGE_LENGTH - 1] = calculate_checksum(&tx_buf, BOOTMESSAGE_LENGTH - 1);
             let interrupt = self.interrupt.next();
@@ -1330,35 +1743,21 @@ impl Module {
     /// The inner Result<Module,Module> indicates whether there was an available update or not.
     async fn update_module(
         mut self,
-        firmwares: &[FirmwareVersion],
+        firmwares: &[FirmwareFile],
         multi_progress: MultiProgress,
         style: ProgressStyle,
     ) -> Result<Result<Self, Self>, UploadError> {
-        if let Some((index, _junk)) = firmwares
-            .iter()
-            .enumerate()
-            .filter(|(_i, available)| available.get_hardware() == self.firmware.get_hardware()) //filter out incorrect hardware versions
-            .filter(|(_i, available)| {
-                (available.get_software() > self.firmware.get_software()
-                    || self.firmware.get_software() == [255u8, 255, 255])
-                    && available.get_software() != [255u8, 255, 255]
-            }) //filter out wrong software versions
-            .map(|(i, available)| (i, available.get_software())) //turn them all into software versions
-            .reduce(|acc, (i, software)| if acc.1 < software { (i, software) } else { acc })
-        //cant use min/max because of the tuple, have to manually compare it in a reduce function
-        {
+        if let Some(file) = latest_update_for(&self, firmwares) {
             println!(
-                "updating slot {} from {} to {}",
+                "updating slot {} from {} to {}{}",
                 self.slot,
                 self.firmware.as_string(),
-                firmwares.get(index).unwrap().as_string()
+                file.version.as_string(),
+                file.cross_revision_note(&self.firmware)
             );
-            match self
-                .overwrite_module(firmwares.get(index).unwrap(), multi_progress, style)
-                .await
-            {
+            match self.overwrite_module(file, multi_progress, style).await {
                 Ok(()) => {
-                    self.firmware = *firmwares.get(index).unwrap();
+                    self.firmware = file.version.flashed_onto(&self.firmware);
                     Ok(Ok(self)) //firmware updated successfully
                 }
                 Err(err) => {
@@ -1452,7 +1851,14 @@ impl Display for Module {
 
 impl Module {
     fn type_name(&self) -> &'static str {
-        let hw = self.firmware.get_hardware();
+        self.firmware.type_name()
+    }
+}
+
+impl FirmwareVersion {
+    /// human readable module type for the hardware part, "Unknown" when not recognised
+    fn type_name(&self) -> &'static str {
+        let hw = self.get_hardware();
         match hw[1] {
             10 => match hw[2] {
                 1 => "6 Channel Input",
@@ -1480,27 +1886,31 @@ impl Module {
     }
 }
 
-/// Among `available`, return the highest-versioned firmware whose hardware
-/// matches `module.firmware` and whose software is strictly newer than the
-/// module's current software. Returns None when no update applies. Also
-/// treats current SW = `[255,255,255]` (sentinel for uninitialized) as
-/// "anything available is an update".
-fn latest_update_for(
+/// Firmware in `available` that fits the hardware of `module.firmware`, built for
+/// its revision or a compatible one (see `compat`), and whose
+/// software is strictly newer than the module's current software. Treats
+/// current SW = `[255,255,255]` (sentinel for uninitialized) as "anything
+/// available is an update".
+fn update_candidates<'a>(
     module: &Module,
-    available: &[FirmwareVersion],
-) -> Option<FirmwareVersion> {
-    let current_sw = module.firmware.get_software();
-    let current_uninit = current_sw == [255u8, 255, 255];
+    available: &'a [FirmwareFile],
+) -> impl Iterator<Item = &'a FirmwareFile> {
+    let current = module.firmware;
+    let current_uninit = current.get_software() == [255u8, 255, 255];
     available
         .iter()
-        .copied()
-        .filter(|f| f.get_hardware() == module.firmware.get_hardware())
-        .filter(|f| f.get_software() != [255u8, 255, 255])
-        .filter(|f| current_uninit || f.get_software() > current_sw)
-        .max_by_key(|f| {
-            let sw = f.get_software();
-            (sw[0], sw[1], sw[2])
-        })
+        .filter(move |f| f.fit(&current).fits())
+        .filter(|f| f.version.get_software() != [255u8, 255, 255])
+        .filter(move |f| current_uninit || f.version.get_software() > current.get_software())
+}
+
+/// The highest-versioned update candidate that the pin policy allows for the
+/// module's slot, preferring the file built for the module's own revision when
+/// several hold that version. Returns None when no update applies.
+fn latest_update_for<'a>(module: &Module, available: &'a [FirmwareFile]) -> Option<&'a FirmwareFile> {
+    update_candidates(module, available)
+        .filter(|f| pin_policy().allows(Some(module.slot), &f.version))
+        .max_by_key(|f| (f.version.get_software(), f.fit(&module.firmware) == HardwareFit::Exact))
 }
 
 /// Format the scanned modules into space-aligned columns:
@@ -1508,8 +1918,10 @@ fn latest_update_for(
 /// String per output row, intended for `show_view`. The "Update" column
 /// shows the highest locally-cached firmware that is newer than the
 /// module's current software (empty when up to date or no firmware
-/// cached — run `go-modules check` to refresh the local cache).
-fn format_module_lines(modules: &[Module], available: &[FirmwareVersion]) -> Vec<String> {
+/// cached — run `go-modules check` to refresh the local cache), with the
+/// revision it was built for when that is another compatible revision, or
+/// the pin when only newer versions outside the module's pin are cached.
+fn format_module_lines(modules: &[Module], available: &[FirmwareFile]) -> Vec<String> {
     let headers = ["Slot", "Type", "HW", "SW Version", "Update"];
 
     let rows: Vec<[String; 5]> = modules
@@ -1518,9 +1930,24 @@ fn format_module_lines(modules: &[Module], available: &[FirmwareVersion]) -> Vec
             let hw = m.firmware.get_hardware();
             let sw = m.firmware.get_software();
             let update_cell = match latest_update_for(m, available) {
-                Some(fw) => {
-                    let nsw = fw.get_software();
-                    format!("→ {}.{}.{}", nsw[0], nsw[1], nsw[2])
+                Some(file) => {
+                    let nsw = file.version.get_software();
+                    match file.fit(&m.firmware) {
+                        HardwareFit::CrossRevision => format!(
+                            "→ {}.{}.{} (HW rev {})",
+                            nsw[0],
+                            nsw[1],
+                            nsw[2],
+                            file.version.get_hardware()[3]
+                        ),
+                        _ => format!("→ {}.{}.{}", nsw[0], nsw[1], nsw[2]),
+                    }
+                }
+                None if update_candidates(m, available).next().is_some() => {
+                    match pin_policy().pin_for(Some(m.slot), &m.firmware) {
+                        Some(pin) => format!("pinned {}", pin.constraint),
+                        None => String::new(),
+                    }
                 }
                 None => String::new(),
             };
@@ -1621,6 +2048,192 @@ where
     a
 }
 
+/// Parse a firmware file into the records sent to the bootloader, one per frame. \
+/// After the S-record validation in `srec::parse` the records are re-blocked to fit in a frame,
+/// so the record width the firmware was built with doesn't matter. On top of that this checks
+/// what the upload protocol needs: the record number fits in the 16 bit line field
+/// and the file ends with an S7 record, which is what tells the bootloader to start the firmware.
+fn firmware_records(content: &str) -> Result<Vec<srec::Record>, String> {
+    upload_records(srec::parse(content).map_err(|e| e.to_string())?)
+}
+
+/// Read a firmware file in any supported format and turn it into upload records, see `firmware_records`.
+fn load_firmware(file: &FirmwareFile) -> Result<Vec<srec::Record>, String> {
+    let data = read_firmware(&file.path).map_err(|e| format!("could not read {}: {e}", file.file_name()))?;
+    decode_firmware(file, &data)
+}
+
+/// Turn the contents of a firmware file into upload records according to its format.
+fn decode_firmware(file: &FirmwareFile, data: &[u8]) -> Result<Vec<srec::Record>, String> {
+    match file.format {
+        FirmwareFormat::Srec | FirmwareFormat::IntelHex => {
+            let text = std::str::from_utf8(data).map_err(|_| "not a text file".to_string())?;
+            if file.format == FirmwareFormat::Srec {
+                check_header_version(file, text)?;
+                firmware_records(text)
+            } else {
+                upload_records(ihex::parse(text)?)
+            }
+        }
+        FirmwareFormat::Binary => {
+            if data.is_empty() {
+                return Err("file is empty".into());
+            }
+            upload_records(srec::from_binary(data, file.base_address.unwrap_or(0)))
+        }
+    }
+}
+
+/// Compare the version in the S0 header, when it holds one, with the version the file is labelled
+/// with (filename, sidecar or --version). A mismatch means a renamed or mislabelled file, and
+/// flashing it would write the wrong software version into the module. `text` only needs to
+/// hold the first line of the file.
+fn check_header_version(file: &FirmwareFile, text: &str) -> Result<(), String> {
+    if file.format != FirmwareFormat::Srec {
+        return Ok(());
+    }
+    match srec::header_text(text).and_then(|h| FirmwareVersion::from_header(&h)) {
+        Some(header) if header != file.version => Err(format!(
+            "S0 header says {} but the file is labelled {}",
+            header.as_string(),
+            file.version.as_string()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The upload checks of `firmware_records` on already parsed records.
+fn upload_records(records: Vec<srec::Record>) -> Result<Vec<srec::Record>, String> {
+    let records = srec::reblock(records, FRAME_RECORD_MAX)?;
+    if records.len() > u16::MAX as usize {
+        return Err(format!(
+            "{} records, the bootloader accepts at most {}",
+            records.len(),
+            u16::MAX
+        ));
+    }
+    match records.last() {
+        Some(last) if last.kind == 7 => Ok(records),
+        Some(last) => Err(format!(
+            "file ends with an S{} record, the bootloader needs an S7 termination record",
+            last.kind
+        )),
+        None => Err("file contains no S-records".into()),
+    }
+}
+
+/// the detached signature belonging to a file: `<file>.sig`
+fn signature_path(path: &Path) -> PathBuf {
+    let mut sig = path.as_os_str().to_owned();
+    sig.push(".sig");
+    PathBuf::from(sig)
+}
+
+/// Ed25519 public keys from `TRUSTED_KEYS_DIR`. Each file holds one hex encoded key,
+/// lines starting with # are comments. Malformed files are skipped.
+fn trusted_keys() -> Vec<Vec<u8>> {
+    let Ok(dir) = fs::read_dir(TRUSTED_KEYS_DIR) else {
+        return Vec::new();
+    };
+    dir.filter_map(|e| e.ok())
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|text| {
+            let key: String = text
+                .lines()
+                .filter(|l| !l.trim_start().starts_with('#'))
+                .collect::<String>()
+                .split_whitespace()
+                .collect();
+            hex::decode(key).ok().filter(|k| k.len() == 32)
+        })
+        .collect()
+}
+
+/// Signatures are 64 bytes, stored either raw or hex encoded.
+fn decode_signature(raw: &[u8]) -> Option<Vec<u8>> {
+    if raw.len() == 64 {
+        return Some(raw.to_vec());
+    }
+    hex::decode(String::from_utf8_lossy(raw).trim())
+        .ok()
+        .filter(|s| s.len() == 64)
+}
+
+/// Check `data` against a detached signature, read from the file at `signature`.
+fn verify_signature(data: &[u8], signature: &Path) -> Result<(), String> {
+    match fs::read(signature) {
+        Ok(raw) => verify_signature_bytes(data, &raw),
+        Err(_) => Err("not signed".into()),
+    }
+}
+
+/// Check `data` against a detached Ed25519 signature made with any of the trusted keys.
+fn verify_signature_bytes(data: &[u8], signature: &[u8]) -> Result<(), String> {
+    let keys = trusted_keys();
+    if keys.is_empty() {
+        return Err(format!("no trusted keys in {TRUSTED_KEYS_DIR}"));
+    }
+    let signature = decode_signature(signature).ok_or("malformed signature")?;
+    if keys.iter().any(|key| {
+        UnparsedPublicKey::new(&ED25519, key)
+            .verify(data, &signature)
+            .is_ok()
+    }) {
+        Ok(())
+    } else {
+        Err("signature does not match a trusted key".into())
+    }
+}
+
+/// The HTTP client for the firmware sources. Requests give up on a server that can't be
+/// reached or stops sending, see `STALL_TIMEOUT`, instead of hanging on a bad link.
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(STALL_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Could not set up the HTTP client: {e}"))
+}
+
+/// A progress bar for downloading `filename`, in bytes.
+fn download_progress(multi_progress: &MultiProgress, filename: &str) -> ProgressBar {
+    let style = ProgressStyle::with_template("{bar:40.cyan/blue} {bytes:>10}/{total_bytes:10} ({eta}) {msg}")
+        .unwrap()
+        .progress_chars("##-")
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| {
+            write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap()
+        });
+    let progress = multi_progress.add(ProgressBar::new(0));
+    progress.set_style(style);
+    progress.set_message(format!("Downloading {filename}"));
+    progress
+}
+
+/// Fetch the detached signature published next to `url` as `<url>.sig`.
+async fn fetch_signature(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, String> {
+    let resp = client
+        .get(format!("{url}.sig"))
+        .timeout(MANIFEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|_| "not signed".to_string())?;
+    resp.bytes()
+        .await
+        .map(|b| b.to_vec())
+        .map_err(|e| format!("signature download failed: {e}"))
+}
+
+/// Apply the signature policy to a verification result: Ok when the signature is valid
+/// or unsigned content is allowed, with a note to show for the latter.
+fn signature_policy(result: Result<(), String>) -> Result<Option<String>, String> {
+    match result {
+        Ok(()) => Ok(None),
+        Err(err) if ALLOW_UNSIGNED.load(Ordering::Relaxed) => Ok(Some(err)),
+        Err(err) => Err(err),
+    }
+}
+
 /// verify the SHA256 checksum of a byte slice against an expected hex string
 fn verify_sha256(data: &[u8], expected_hex: &str) -> bool {
     let mut hasher = Sha256::new();
@@ -1655,188 +2268,1772 @@ struct CheckEntry {
     status: String,
     released: Option<String>,
     changelog: Option<String>,
+    /// remote source the release came from, empty when not relevant
+    source: String,
 }
 
-/// Fetch the latest firmware files from the GOcontroll cloud, save them locally
-/// after SHA256 validation, and return the human-readable status lines (one per
-/// module, plus optional Released/Changes detail lines when `verbose`).
-///
-/// All output is returned rather than printed so the caller can render it
-/// inside the frame view.
-async fn check_firmware(verbose: bool) -> Result<Vec<String>, String> {
-    let client = reqwest::Client::new();
+impl CheckEntry {
+    fn failed(name: String, hw: String, status: String, source: &FirmwareSource) -> Self {
+        Self {
+            name,
+            hw,
+            sw: String::new(),
+            status,
+            released: None,
+            changelog: None,
+            source: source.to_string(),
+        }
+    }
+}
 
-    // Fetch main manifest
-    let main_manifest: CloudMainManifest = client
-        .get(format!("{}/modules/manifest.json", CLOUD_BASE_URL))
+/// Fetch and verify the main manifest at `base_url`. It decides which files get
+/// downloaded so it has to be signed.
+async fn fetch_main_manifest(client: &reqwest::Client, base_url: &str) -> Result<CloudMainManifest, String> {
+    let main_url = format!("{}/modules/manifest.json", base_url);
+    let main_bytes = client
+        .get(&main_url)
+        .timeout(MANIFEST_TIMEOUT)
         .send()
         .await
+        .and_then(|r| r.error_for_status())
         .map_err(|e| format!("{e}"))?
-        .json()
+        .bytes()
         .await
         .map_err(|e| format!("{e}"))?;
+    let main_signature = fetch_signature(client, &main_url).await;
+    signature_policy(main_signature.and_then(|sig| verify_signature_bytes(&main_bytes, &sig)))
+        .map_err(|e| format!("manifest signature: {e}"))?;
+    serde_json::from_slice(&main_bytes).map_err(|e| format!("{e}"))
+}
 
-    // Ensure firmware directory exists
-    fs::create_dir_all(FIRMWARE_DIR)
-        .map_err(|e| format!("Could not create firmware directory {FIRMWARE_DIR}: {e}"))?;
+/// The copy of a release file in `dir`, the firmware directory or a local directory source:
+/// None when there is none, an error naming the problem when it no longer matches the
+/// release's SHA256 or lacks a valid signature (unless unsigned content is allowed).
+fn local_copy_state(dir: &Path, filename: &str, release: &CloudRelease) -> Option<Result<(), &'static str>> {
+    // Downloads are stored compressed, a file downloaded before that is still used
+    let plain = dir.join(filename);
+    let local_path = match compressed_path(&plain) {
+        compressed if compressed.is_file() || !plain.is_file() => compressed,
+        _ => plain,
+    };
+    let existing_data = read_firmware(&local_path).ok()?;
+    Some(if !verify_sha256(&existing_data, &release.sha256) {
+        Err("local file corrupted")
+    } else if verify_signature(&existing_data, &signature_path(&local_path)).is_err()
+        && !ALLOW_UNSIGNED.load(Ordering::Relaxed)
+    {
+        Err("local signature missing")
+    } else {
+        Ok(())
+    })
+}
 
-    let mut entries: Vec<CheckEntry> = Vec::with_capacity(main_manifest.modules.len());
+/// Download `release` of a remote source into the firmware directory as `filename`: fetched
+/// from `endpoint` first, checked against its SHA256 and the signature policy, then stored
+/// compressed with its metadata in the sidecar. Returns the reason when unsigned content was
+/// accepted, or the status explaining why nothing was stored.
+///
+/// The data goes to `<filename>.part` first. A download that breaks off leaves it behind
+/// and the next attempt asks for the rest with an HTTP Range request; a server that doesn't
+/// support that, or won't resume, sends the whole file again.
+async fn download_release(
+    client: &reqwest::Client,
+    source: &FirmwareSource,
+    endpoint: &str,
+    release: &CloudRelease,
+    filename: &str,
+    progress: &ProgressBar,
+) -> Result<Option<String>, String> {
+    let part = Path::new(FIRMWARE_DIR).join(format!("{filename}.part"));
+    let mut offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+    let fetched = match fetch_from_endpoints(client, source, endpoint, &release.file, offset).await {
+        Err(_) if offset > 0 => {
+            offset = 0;
+            fetch_from_endpoints(client, source, endpoint, &release.file, 0).await
+        }
+        fetched => fetched,
+    };
+    let (file_url, mut resp) = fetched.map_err(|e| format!("download failed: {e}"))?;
+    let resumed = offset > 0 && resp.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    if !resumed {
+        offset = 0;
+    }
+    let remaining = resp.content_length().unwrap_or(0);
+    if !has_room_for(remaining) {
+        return Err(format!(
+            "skipped, less than {} MiB would be left free in {FIRMWARE_DIR}, \
+             try `go-modules firmware prune`",
+            FIRMWARE_MIN_FREE_SPACE / 1024 / 1024
+        ));
+    }
 
-    for entry in &main_manifest.modules {
-        // Fetch per-module sub-manifest
-        let sub_manifest: CloudModuleManifest = match client
-            .get(format!("{}/{}", CLOUD_BASE_URL, entry.manifest))
-            .send()
-            .await
-        {
-            Ok(resp) => match resp.json().await {
-                Ok(m) => m,
-                Err(e) => {
-                    entries.push(CheckEntry {
-                        name: format!("(manifest {})", entry.manifest),
-                        hw: String::new(),
-                        sw: String::new(),
-                        status: format!("parse failed: {e}"),
-                        released: None,
-                        changelog: None,
-                    });
-                    continue;
-                }
-            },
-            Err(e) => {
-                entries.push(CheckEntry {
-                    name: format!("(manifest {})", entry.manifest),
-                    hw: String::new(),
-                    sw: String::new(),
-                    status: format!("fetch failed: {e}"),
-                    released: None,
-                    changelog: None,
-                });
-                continue;
+    let mut part_file = if resumed {
+        fs::OpenOptions::new().append(true).open(&part)
+    } else {
+        File::create(&part)
+    }
+    .map_err(|e| format!("could not write {}: {e}", part.display()))?;
+    progress.set_length(offset + remaining);
+    progress.set_position(offset);
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|e| {
+            if e.is_timeout() {
+                format!("download stalled for {}s, resumes on the next attempt", STALL_TIMEOUT.as_secs())
+            } else {
+                format!("download interrupted, resumes on the next attempt: {e}")
             }
-        };
+        })?
+    {
+        part_file
+            .write_all(&chunk)
+            .map_err(|e| format!("could not write {}: {e}", part.display()))?;
+        progress.inc(chunk.len() as u64);
+    }
+    drop(part_file);
+    let data = fs::read(&part).map_err(|e| format!("could not read {}: {e}", part.display()))?;
+    // complete either way, a bad file must not be resumed
+    let _ = fs::remove_file(&part);
 
-        // The first entry in releases is the latest version
-        let latest = match sub_manifest.releases.first() {
-            Some(r) => r,
-            None => {
-                entries.push(CheckEntry {
-                    name: sub_manifest.name,
-                    hw: sub_manifest.hardware_version,
-                    sw: String::new(),
-                    status: "no releases found".into(),
-                    released: None,
-                    changelog: None,
-                });
-                continue;
-            }
-        };
+    if !verify_sha256(&data, &release.sha256) {
+        return Err("checksum verification failed".into());
+    }
+    let signature = fetch_signature(client, &file_url).await;
+    let signed = signature
+        .as_ref()
+        .map_err(|e| e.clone())
+        .and_then(|sig| verify_signature_bytes(&data, sig));
+    let unsigned = signature_policy(signed).map_err(|e| format!("signature verification failed: {e}"))?;
+    let path = store_firmware(filename, &data, signature.as_deref().ok())
+        .map_err(|e| format!("could not save {filename}: {e}"))?;
+    write_sidecar(&path, &source.to_string(), release);
+    Ok(unsigned)
+}
 
-        // Extract filename from the cloud file path
-        let filename = match latest.file.split('/').last() {
-            Some(f) if !f.is_empty() => f,
-            _ => {
-                entries.push(CheckEntry {
-                    name: sub_manifest.name,
-                    hw: sub_manifest.hardware_version,
-                    sw: latest.sw_version.clone(),
-                    status: format!("invalid file path: {}", latest.file),
-                    released: None,
-                    changelog: None,
-                });
-                continue;
+/// What `check` fetches: firmware for the `fitted` modules, or everything with `all` (`--all`)
+/// or when no modules are known, e.g. on a fresh image.
+fn check_scope(fitted: &[FirmwareVersion], all: bool) -> Option<&[FirmwareVersion]> {
+    (!fitted.is_empty() && !all).then_some(fitted)
+}
+
+/// The firmware of the fitted modules for `check --scan`: scanned, with the services that
+/// use the modules stopped during the scan. When the scan hits hardware errors, the modules
+/// in modules.json from the last scan.
+async fn scanned_firmware() -> Vec<FirmwareVersion> {
+    let controller = detect_controller();
+    let nodered = is_service_active("nodered");
+    let simulink = is_service_active("go-simulink");
+    let hardware_driver = is_service_active("go-hardware-driver");
+    for (running, service) in [(nodered, "nodered"), (simulink, "go-simulink"), (hardware_driver, "go-hardware-driver")] {
+        if running {
+            stop_service(service);
+        }
+    }
+    if let Err(err) = ctrlc::set_handler(move || {
+        restart_services(nodered, simulink, hardware_driver);
+        exit(-1);
+    }) {
+        eprintln!("couldn't set sigint handler: {}", err);
+    }
+    let modules = get_modules_and_save(controller).await;
+    restart_services(nodered, simulink, hardware_driver);
+    if SCAN_HAD_ERRORS.load(Ordering::Relaxed) {
+        eprintln!("The scan failed, using the modules from the last scan");
+        return installed_firmware().into_iter().map(|(_, fw)| fw).collect();
+    }
+    modules.iter().map(|m| m.firmware).collect()
+}
+
+/// Request `path` from the endpoints of a remote source in order, starting with `first`,
+/// the one that served the manifests, from byte `offset` on when it isn't 0. Returns the
+/// URL that answered with its response.
+async fn fetch_from_endpoints(
+    client: &reqwest::Client,
+    source: &FirmwareSource,
+    first: &str,
+    path: &str,
+    offset: u64,
+) -> Result<(String, reqwest::Response), String> {
+    let mut endpoints = source.base_urls(cloud_endpoints());
+    endpoints.retain(|e| *e != first);
+    endpoints.insert(0, first);
+    let mut last_error = String::new();
+    for base_url in endpoints {
+        let url = format!("{base_url}/{path}");
+        let mut request = client.get(&url);
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={offset}-"));
+        }
+        match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(resp) => return Ok((url, resp)),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+/// The module manifests of one remote source, see `fetch_manifests`.
+struct FetchedManifests<'a> {
+    /// endpoint of the source that served them
+    endpoint: &'a str,
+    /// update date of the main manifest
+    updated: String,
+    manifests: Vec<CloudModuleManifest>,
+    /// module manifests left out because no fitted module can use them
+    skipped: usize,
+}
+
+/// Fetch the main manifest of a remote source and the module manifests it lists.
+/// The source's endpoints are tried in order, the first one with a valid main manifest
+/// serves the module manifests. A module manifest that can't be fetched or verified
+/// becomes an error entry. With `present`, only the manifests with firmware for those
+/// modules are kept, see `manifest_relevant`.
+async fn fetch_manifests<'a>(
+    client: &reqwest::Client,
+    source: &'a FirmwareSource,
+    present: Option<&[FirmwareVersion]>,
+    entries: &mut Vec<CheckEntry>,
+) -> Result<FetchedManifests<'a>, String> {
+    let endpoints = source.base_urls(cloud_endpoints());
+    let mut errors = Vec::new();
+    let mut found = None;
+    for base_url in &endpoints {
+        match fetch_main_manifest(client, base_url).await {
+            Ok(manifest) => {
+                found = Some((*base_url, manifest));
+                break;
+            }
+            Err(e) if endpoints.len() == 1 => errors.push(e),
+            Err(e) => errors.push(format!("{base_url}: {e}")),
+        }
+    }
+    let Some((base_url, main_manifest)) = found else {
+        return Err(errors.join(", "));
+    };
+
+    // a different module type can't be compatible, no need to fetch its manifest
+    let other_type = |entry: &CloudModuleEntry, present: &[FirmwareVersion]| {
+        entry
+            .hardware_version
+            .as_deref()
+            .and_then(hardware_from_string)
+            .is_some_and(|hw| {
+                !present
+                    .iter()
+                    .any(|m| m.get_hardware()[..3] == hw.get_hardware()[..3])
+            })
+    };
+    let wanted: Vec<&CloudModuleEntry> = main_manifest
+        .modules
+        .iter()
+        .filter(|entry| !present.is_some_and(|p| other_type(entry, p)))
+        .collect();
+    let mut skipped = main_manifest.modules.len() - wanted.len();
+    let fetched: Vec<_> = stream::iter(wanted)
+        .map(|entry| async move {
+            let url = format!("{}/{}", base_url, entry.manifest);
+            (entry, fetch_module_manifest(client, &url).await)
+        })
+        .buffered(PARALLEL_DOWNLOADS)
+        .collect()
+        .await;
+
+    let mut manifests = Vec::with_capacity(fetched.len());
+    for (entry, result) in fetched {
+        match result {
+            Ok(m) if present.is_some_and(|p| !manifest_relevant(&m, p)) => skipped += 1,
+            Ok(m) => {
+                cache_manifest(&m);
+                manifests.push(m);
+            }
+            Err(e) => entries.push(CheckEntry::failed(
+                format!("(manifest {})", entry.manifest),
+                String::new(),
+                e,
+                source,
+            )),
+        }
+    }
+    Ok(FetchedManifests {
+        endpoint: base_url,
+        updated: main_manifest.updated,
+        manifests,
+        skipped,
+    })
+}
+
+/// Fetch a module manifest and its signature, returning the manifest or the status to report.
+async fn fetch_module_manifest(client: &reqwest::Client, url: &str) -> Result<CloudModuleManifest, String> {
+    let (signature, resp) = futures::join!(
+        fetch_signature(client, url),
+        client.get(url).timeout(MANIFEST_TIMEOUT).send()
+    );
+    let text = resp
+        .map_err(|e| format!("fetch failed: {e}"))?
+        .text()
+        .await
+        .map_err(|e| format!("parse failed: {e}"))?;
+    signature_policy(signature.and_then(|sig| verify_signature_bytes(text.as_bytes(), &sig)))
+        .map_err(|e| format!("parse failed: signature: {e}"))?;
+    serde_json::from_str(&text).map_err(|e| format!("parse failed: {e}"))
+}
+
+/// Parse a manifest hardware version like 20-10-1-5 into a firmware version with
+/// software 0.0.0.
+fn hardware_from_string(hardware: &str) -> Option<FirmwareVersion> {
+    FirmwareVersion::from_filename(format!("{hardware}-0-0-0"))
+}
+
+/// Whether a module manifest has firmware for one of the `present` modules: built for
+/// their hardware, or for a revision the compatibility rules or one of its releases
+/// declare compatible. A manifest with an unreadable hardware version is kept.
+fn manifest_relevant(manifest: &CloudModuleManifest, present: &[FirmwareVersion]) -> bool {
+    let Some(built_for) = hardware_from_string(&manifest.hardware_version) else {
+        return true;
+    };
+    let declared: Vec<Option<RevisionRange>> = std::iter::once(None)
+        .chain(manifest.releases.iter().map(CloudRelease::declared_revisions))
+        .collect();
+    present.iter().any(|module| {
+        declared
+            .iter()
+            .any(|d| compatibility().fit(&built_for, *d, module).fits())
+    })
+}
+
+/// The module manifests of all remote sources, see `fetch_remote_manifests`.
+struct RemoteManifests {
+    /// number of remote sources configured
+    sources: usize,
+    /// update date of the first main manifest fetched
+    updated: String,
+    manifests: Vec<(&'static FirmwareSource, CloudModuleManifest)>,
+    /// the endpoint of each source that served its manifests, files are fetched from it first
+    served_by: Vec<(&'static FirmwareSource, &'static str)>,
+    /// the sources that could not be reached, with the reason
+    failures: Vec<String>,
+    /// module manifests left out because no fitted module can use them
+    skipped: usize,
+}
+
+/// Fetch the module manifests of every remote source, see `fetch_manifests`. An unreachable
+/// source is noted in `failures` when another one answers, an error when none does.
+async fn fetch_remote_manifests(
+    client: &reqwest::Client,
+    present: Option<&[FirmwareVersion]>,
+    entries: &mut Vec<CheckEntry>,
+) -> Result<RemoteManifests, String> {
+    let remotes: Vec<&'static FirmwareSource> = firmware_sources()
+        .iter()
+        .filter(|s| s.is_remote())
+        .collect();
+    if remotes.is_empty() {
+        return Err(format!("no remote firmware source configured in {CONFIG_PATH}"));
+    }
+
+    let mut updated: Option<String> = None;
+    let mut manifests = Vec::new();
+    let mut served_by = Vec::new();
+    let mut failures: Vec<String> = Vec::new();
+    let mut skipped = 0;
+    // all sources at once, their results and error entries kept in the configured order
+    let fetched = join_all(remotes.iter().map(|source| async move {
+        let mut source_entries = Vec::new();
+        let result = fetch_manifests(client, source, present, &mut source_entries).await;
+        (source, result, source_entries)
+    }))
+    .await;
+    for (source, result, source_entries) in fetched {
+        entries.extend(source_entries);
+        match result {
+            Ok(fetched) => {
+                updated.get_or_insert(fetched.updated);
+                manifests.extend(fetched.manifests.into_iter().map(|m| (*source, m)));
+                served_by.push((*source, fetched.endpoint));
+                skipped += fetched.skipped;
+            }
+            Err(e) if remotes.len() == 1 => return Err(e),
+            Err(e) => failures.push(format!("{source}: {e}")),
+        }
+    }
+    let Some(updated) = updated else {
+        return Err(failures.join("\n"));
+    };
+    Ok(RemoteManifests {
+        sources: remotes.len(),
+        updated,
+        manifests,
+        served_by,
+        failures,
+        skipped,
+    })
+}
+
+/// Fetch the latest firmware files from the remote firmware sources, save them
+/// locally after SHA256 validation, and return the human-readable status lines
+/// (one per module, plus optional Released/Changes detail lines when `verbose`).
+///
+/// The module manifests of all remote sources are merged per hardware: the
+/// newest release the version pins allow wins, on equal versions the source
+/// listed first. A file a local directory source already provides is not
+/// downloaded.
+///
+/// With `present`, the firmware of the modules fitted in the controller, only
+/// the manifests and files those modules can use are fetched, see `check_scope`.
+///
+/// All output is returned rather than printed so the caller can render it
+/// inside the frame view.
+async fn check_firmware(verbose: bool, present: Option<&[FirmwareVersion]>) -> Result<Vec<String>, String> {
+    let client = http_client()?;
+    let mut entries: Vec<CheckEntry> = Vec::new();
+    let RemoteManifests {
+        sources: remotes,
+        updated,
+        manifests,
+        served_by,
+        failures,
+        skipped,
+    } = fetch_remote_manifests(&client, present, &mut entries).await?;
+
+    // Ensure firmware directory exists
+    fs::create_dir_all(FIRMWARE_DIR)
+        .map_err(|e| format!("Could not create firmware directory {FIRMWARE_DIR}: {e}"))?;
+
+    let installed = installed_firmware();
+    // Releases are listed newest first. Take the newest one the pin policy allows for
+    // every slot whose hardware it fits according to modules.json, or for any slot
+    // when none does.
+    let allowed = |release: &&CloudRelease| {
+        let Some(fw) = release.version() else {
+            return true;
+        };
+        let declared = release.declared_revisions();
+        let slots: Vec<u8> = installed
+            .iter()
+            .filter(|(_, installed)| compatibility().fit(&fw, declared, installed).fits())
+            .map(|(slot, _)| *slot)
+            .collect();
+        if slots.is_empty() {
+            pin_policy().allows(None, &fw)
+        } else {
+            slots.iter().all(|slot| pin_policy().allows(Some(*slot), &fw))
+        }
+    };
+    let release_version = |release: &CloudRelease| {
+        release
+            .version()
+            .map(|fw| fw.get_software().to_vec())
+            .unwrap_or_default()
+    };
+
+    // Merge per hardware, keeping the order in which the hardware was first seen
+    let mut hardware: Vec<&str> = Vec::new();
+    for (_, manifest) in &manifests {
+        if !hardware.contains(&manifest.hardware_version.as_str()) {
+            hardware.push(&manifest.hardware_version);
+        }
+    }
+    let local_dirs: Vec<&PathBuf> = firmware_sources()
+        .iter()
+        .filter(|s| !s.is_firmware_dir())
+        .filter_map(|s| match s {
+            FirmwareSource::LocalDir { path } => Some(path),
+            _ => None,
+        })
+        .collect();
+
+    // copies in local directories that don't match their release
+    let mut stale_copies: Vec<String> = Vec::new();
+    // files to fetch, with the index of their entry
+    let mut downloads: Vec<(usize, &FirmwareSource, &CloudRelease, &str)> = Vec::new();
+    for hw in hardware {
+        let offers: Vec<(&FirmwareSource, &CloudModuleManifest)> = manifests
+            .iter()
+            .filter(|(_, m)| m.hardware_version == hw)
+            .map(|(source, m)| (*source, m))
+            .collect();
+        let best = offers
+            .iter()
+            .filter_map(|(source, m)| Some((*source, *m, m.releases.iter().find(allowed)?)))
+            .reduce(|best, offer| {
+                if release_version(offer.2) > release_version(best.2) {
+                    offer
+                } else {
+                    best
+                }
+            });
+        let (source, sub_manifest, latest) = match best {
+            Some(best) => best,
+            None => {
+                let (source, manifest) = offers[0];
+                let status = if offers.iter().any(|(_, m)| !m.releases.is_empty()) {
+                    "no release matches the version pins"
+                } else {
+                    "no releases found"
+                };
+                entries.push(CheckEntry::failed(
+                    manifest.name.clone(),
+                    manifest.hardware_version.clone(),
+                    status.into(),
+                    source,
+                ));
+                continue;
+            }
+        };
+
+        // Extract filename from the remote file path
+        let filename = match latest.file_name() {
+            Some(f) => f,
+            None => {
+                entries.push(CheckEntry {
+                    name: sub_manifest.name.clone(),
+                    hw: sub_manifest.hardware_version.clone(),
+                    sw: latest.sw_version.clone(),
+                    status: format!("invalid file path: {}", latest.file),
+                    released: None,
+                    changelog: None,
+                    source: source.to_string(),
+                });
+                continue;
             }
         };
 
-        let local_path = format!("{}{}", FIRMWARE_DIR, filename);
         let mut status = String::new();
         let mut needs_download = true;
 
-        if let Ok(existing_data) = fs::read(&local_path) {
-            if verify_sha256(&existing_data, &latest.sha256) {
-                status = "up to date".into();
-                needs_download = false;
-            } else {
-                status = "local file corrupted, re-downloading...".into();
+        // a local directory only provides the release when its copy is the release's file
+        let local_copies: Vec<(&PathBuf, Option<Result<(), &str>>)> = local_dirs
+            .iter()
+            .map(|dir| (*dir, local_copy_state(dir, filename, latest)))
+            .collect();
+        if let Some((dir, _)) = local_copies.iter().find(|(_, state)| *state == Some(Ok(()))) {
+            status = format!("provided by {}", dir.display());
+            needs_download = false;
+        } else {
+            match local_copy_state(Path::new(FIRMWARE_DIR), filename, latest) {
+                Some(Ok(())) => {
+                    status = "up to date".into();
+                    needs_download = false;
+                }
+                Some(Err(reason)) => status = format!("{reason}, re-downloading..."),
+                None => (),
+            }
+            for (dir, state) in &local_copies {
+                if let Some(Err(reason)) = state {
+                    stale_copies.push(format!("{}: {reason}", dir.join(filename).display()));
+                }
+            }
+        }
+
+        if needs_download {
+            downloads.push((entries.len(), source, latest, filename));
+        }
+
+        entries.push(CheckEntry {
+            name: sub_manifest.name.clone(),
+            hw: sub_manifest.hardware_version.clone(),
+            sw: latest.sw_version.clone(),
+            status,
+            released: Some(latest.date.clone()),
+            changelog: Some(latest.changelog.clone()),
+            source: source.to_string(),
+        });
+    }
+
+    let multi_progress = MultiProgress::new();
+    let results: Vec<_> = stream::iter(downloads)
+        .map(|(index, source, release, filename)| {
+            let (client, multi_progress, served_by) = (&client, &multi_progress, &served_by);
+            async move {
+                let first = served_by
+                    .iter()
+                    .find(|(s, _)| *s == source)
+                    .map(|(_, endpoint)| *endpoint)
+                    .unwrap_or_default();
+                let progress = download_progress(multi_progress, filename);
+                let result = download_release(client, source, first, release, filename, &progress).await;
+                progress.finish_and_clear();
+                (index, result)
+            }
+        })
+        .buffer_unordered(PARALLEL_DOWNLOADS)
+        .collect()
+        .await;
+    for (index, result) in results {
+        let entry = &mut entries[index];
+        entry.status = match result {
+            Ok(unsigned) => {
+                let mut done = if entry.status.is_empty() { "downloaded" } else { "re-downloaded" }.to_string();
+                if let Some(reason) = unsigned {
+                    let _ = write!(done, " (unsigned: {reason})");
+                }
+                done
+            }
+            Err(e) => e,
+        };
+    }
+
+    // Compute column widths for space alignment.
+    let name_w = entries.iter().map(|e| e.name.len()).max().unwrap_or(0);
+    let hw_w = entries.iter().map(|e| e.hw.len()).max().unwrap_or(0);
+    let sw_w = entries.iter().map(|e| e.sw.len()).max().unwrap_or(0);
+
+    let mut out = Vec::with_capacity(entries.len() * if verbose { 3 } else { 1 } + 2);
+    out.push(format!(
+        "Cloud manifest last updated: {}",
+        updated
+    ));
+    out.push(String::new());
+    for failure in &failures {
+        out.push(format!("Skipped {failure}"));
+    }
+    for (source, endpoint) in &served_by {
+        if source.base_urls(cloud_endpoints()).first() != Some(endpoint) {
+            out.push(format!("{source} served by mirror {endpoint}"));
+        }
+    }
+    for copy in &stale_copies {
+        out.push(format!("Ignored {copy}"));
+    }
+    if skipped > 0 {
+        out.push(format!(
+            "Skipped {skipped} module manifests without firmware for the fitted modules, use --all for a complete library"
+        ));
+    }
+
+    for e in &entries {
+        let mut line = String::new();
+        let _ = write!(line, "{:<name_w$}", e.name, name_w = name_w);
+        if hw_w > 0 {
+            let _ = write!(line, "  HW {:<hw_w$}", e.hw, hw_w = hw_w);
+        }
+        if sw_w > 0 {
+            let _ = write!(
+                line,
+                "  v{:<sw_w$}",
+                if e.sw.is_empty() { "" } else { &e.sw },
+                sw_w = sw_w
+            );
+        }
+        let _ = write!(line, "  {}", e.status);
+        if remotes > 1 && !e.source.is_empty() {
+            let _ = write!(line, " ({})", e.source);
+        }
+        out.push(line);
+
+        if verbose {
+            if let Some(r) = &e.released {
+                out.push(format!("  Released: {r}"));
+            }
+            if let Some(c) = &e.changelog {
+                out.push(format!("  Changes:  {c}"));
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Which module hardware a `releases` or `download` argument names: a slot number (the module
+/// recorded there by the last scan), a hardware version like 20-10-1-5 or a module type like
+/// output-6ch. Returns the hardware, or the module type when only that is given.
+fn release_target(arg: &str) -> Result<(Option<FirmwareVersion>, Option<ModuleType>), String> {
+    if let Ok(slot) = arg.parse::<u8>() {
+        return installed_firmware()
+            .into_iter()
+            .find(|(s, _)| *s == slot)
+            .map(|(_, fw)| (Some(fw), None))
+            .ok_or_else(|| format!("No module recorded in slot {slot}, run `go-modules scan` first"));
+    }
+    if let Some(fw) = hardware_from_string(arg) {
+        return Ok((Some(fw), None));
+    }
+    serde_json::from_value::<ModuleType>(Value::String(arg.to_string()))
+        .map(|module_type| (None, Some(module_type)))
+        .map_err(|_| format!("{arg} is not a slot, hardware version or module type"))
+}
+
+/// `releases <slot|hardware|module type>`: every release the remote sources offer for the
+/// module, not only the newest one `check` downloads, with dates, changelogs and whether
+/// the version is in the library or installed.
+async fn list_releases(arg: &str) -> Result<Vec<String>, String> {
+    let (hardware, module_type) = release_target(arg)?;
+    let client = http_client()?;
+    let mut entries = Vec::new();
+    let present = hardware.map(|fw| [fw]);
+    let remote = fetch_remote_manifests(&client, present.as_ref().map(|p| p.as_slice()), &mut entries).await?;
+    let library = read_firmware_dir();
+    let installed = installed_firmware();
+
+    let mut out = Vec::new();
+    for failure in &remote.failures {
+        out.push(format!("Skipped {failure}"));
+    }
+    for entry in &entries {
+        out.push(format!("Skipped {} ({}): {}", entry.name, entry.source, entry.status));
+    }
+    for (source, manifest) in &remote.manifests {
+        let built_for = hardware_from_string(&manifest.hardware_version);
+        if let Some(module_type) = module_type {
+            if built_for.and_then(|fw| ModuleType::from_firmware(&fw)) != Some(module_type) {
+                continue;
+            }
+        }
+        if !out.is_empty() {
+            out.push(String::new());
+        }
+        out.push(format!("{}  HW {}  ({source})", manifest.name, manifest.hardware_version));
+        if manifest.releases.is_empty() {
+            out.push("  no releases".into());
+        }
+        for release in &manifest.releases {
+            let mut marks = Vec::new();
+            if let Some(fw) = release.version() {
+                if library.iter().any(|f| f.version == fw) {
+                    marks.push("in library".to_string());
+                }
+                let slots: Vec<String> = installed
+                    .iter()
+                    .filter(|(_, m)| {
+                        m.get_software() == fw.get_software()
+                            && compatibility().fit(&fw, release.declared_revisions(), m).fits()
+                    })
+                    .map(|(slot, _)| slot.to_string())
+                    .collect();
+                if !slots.is_empty() {
+                    marks.push(format!("installed in slot {}", slots.join(", ")));
+                }
+                if hardware.is_some_and(|m| compatibility().fit(&fw, release.declared_revisions(), &m) == HardwareFit::CrossRevision) {
+                    marks.push(format!("cross-revision, built for HW rev {}", fw.get_hardware()[3]));
+                }
+            }
+            out.push(
+                format!("  v{:<8}  {}  {}", release.sw_version, release.date, marks.join(", "))
+                    .trim_end()
+                    .to_string(),
+            );
+            if !release.changelog.is_empty() {
+                out.push(format!("             {}", release.changelog));
+            }
+        }
+    }
+    if remote.manifests.is_empty() || out.iter().all(|l| l.starts_with("Skipped")) {
+        out.push(format!("No releases found for {arg}"));
+    }
+    Ok(out)
+}
+
+/// `download <hardware> <version>`: fetch one specific release, e.g. an older version a
+/// certified machine needs, into the library through the same checks as `check`. Pins don't
+/// apply, they govern what gets installed, not what is kept. A release built for the hardware
+/// itself is preferred over one for a compatible revision.
+async fn download_version(hardware: &str, version: &str) -> Result<Vec<String>, String> {
+    let (Some(module), None) = release_target(hardware)? else {
+        return Err(format!("download needs a slot or a hardware version like 20-10-1-5, not {hardware}"));
+    };
+    let wanted: Vec<u8> = version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|n| n.parse::<u8>())
+        .collect::<Result<_, _>>()
+        .ok()
+        .filter(|v: &Vec<u8>| v.len() == 3)
+        .ok_or_else(|| format!("Invalid software version {version}, expected x.y.z"))?;
+    let client = http_client()?;
+    let mut entries = Vec::new();
+    let remote = fetch_remote_manifests(&client, Some(&[module]), &mut entries).await?;
+
+    let mut offers: Vec<(&FirmwareSource, &CloudRelease, HardwareFit)> = remote
+        .manifests
+        .iter()
+        .flat_map(|(source, manifest)| manifest.releases.iter().map(move |r| (*source, r)))
+        .filter_map(|(source, release)| {
+            let fw = release.version()?;
+            let fit = compatibility().fit(&fw, release.declared_revisions(), &module);
+            (fw.get_software() == wanted && fit.fits()).then_some((source, release, fit))
+        })
+        .collect();
+    // stable sort keeps the source order for equal fits
+    offers.sort_by_key(|(_, _, fit)| *fit != HardwareFit::Exact);
+    let Some((source, release, fit)) = offers.first().copied() else {
+        // keyed on the software version so the list is in version order without repeats
+        let available: BTreeMap<Vec<u8>, &str> = remote
+            .manifests
+            .iter()
+            .flat_map(|(_, m)| &m.releases)
+            .filter_map(|r| {
+                let fw = r.version()?;
+                compatibility()
+                    .fit(&fw, r.declared_revisions(), &module)
+                    .fits()
+                    .then(|| (fw.get_software().to_vec(), r.sw_version.as_str()))
+            })
+            .collect();
+        let available: Vec<&str> = available.into_values().collect();
+        return Err(format!(
+            "No release {version} for {}, available: {}",
+            hardware_string(&module),
+            if available.is_empty() { "none".to_string() } else { available.join(", ") }
+        ));
+    };
+    let filename = release.file_name().unwrap_or_default();
+    let note = match fit {
+        HardwareFit::CrossRevision => format!(
+            " (cross-revision, built for HW rev {})",
+            release.version().map(|fw| fw.get_hardware()[3]).unwrap_or_default()
+        ),
+        _ => String::new(),
+    };
+    fs::create_dir_all(FIRMWARE_DIR)
+        .map_err(|e| format!("Could not create firmware directory {FIRMWARE_DIR}: {e}"))?;
+    if local_copy_state(Path::new(FIRMWARE_DIR), filename, release) == Some(Ok(())) {
+        return Ok(vec![format!("{filename}: already in the library{note}")]);
+    }
+    let endpoint = remote
+        .served_by
+        .iter()
+        .find(|(s, _)| *s == source)
+        .map(|(_, endpoint)| *endpoint)
+        .unwrap_or_default();
+    let progress = download_progress(&MultiProgress::new(), filename);
+    let result = download_release(&client, source, endpoint, release, filename, &progress).await;
+    progress.finish_and_clear();
+    let unsigned = result.map_err(|e| format!("{filename}: {e}"))?;
+    let mut line = format!("{filename}: downloaded from {source}{note}");
+    if let Some(reason) = unsigned {
+        let _ = write!(line, " (unsigned: {reason})");
+    }
+    Ok(vec![line])
+}
+
+/// Record the source and release metadata of a downloaded or imported file in its
+/// `<file>.json` sidecar, keeping the fields set by hand. Best effort, without it
+/// the file only loses its metadata.
+fn write_sidecar(path: &Path, source: &str, release: &CloudRelease) {
+    let mut sidecar = read_sidecar(path).unwrap_or_default();
+    sidecar.source = Some(source.to_string());
+    sidecar.date = Some(release.date.clone());
+    sidecar.changelog = Some(release.changelog.clone());
+    sidecar.sha256 = Some(release.sha256.to_lowercase());
+    sidecar.downloaded = Some(utc_now());
+    // an unreadable range would make the catalogue skip the file, drop it instead
+    sidecar.compatible_revisions = release
+        .compatible_revisions
+        .clone()
+        .filter(|r| RevisionRange::parse(r).is_ok());
+    if let Ok(json) = serde_json::to_string_pretty(&sidecar) {
+        _ = write_atomic(&sidecar_path(path), json.as_bytes());
+    }
+}
+
+/// The current time as `YYYY-MM-DD HH:MM:SS UTC`.
+fn utc_now() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    // days since 1970-01-01 to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs % 86_400 / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Keep a copy of a module manifest for offline checksum verification. Releases cached
+/// for the same hardware that `manifest` doesn't list are kept, they can come from another
+/// source or an earlier bundle and their files may still be in the library.
+/// Best effort, a failure only means `verify` can't check the checksums.
+fn cache_manifest(manifest: &CloudModuleManifest) {
+    let path = Path::new(MANIFEST_CACHE_DIR).join(format!("{}.json", manifest.hardware_version.replace('/', "_")));
+    let file_name = |r: &CloudRelease| r.file.split('/').next_back().map(str::to_string);
+    let mut releases = manifest.releases.clone();
+    if let Some(cached) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| serde_json::from_str::<CloudModuleManifest>(&text).ok())
+    {
+        releases.extend(
+            cached
+                .releases
+                .into_iter()
+                .filter(|r| !manifest.releases.iter().any(|n| file_name(n) == file_name(r))),
+        );
+    }
+    let merged = CloudModuleManifest {
+        name: manifest.name.clone(),
+        hardware_version: manifest.hardware_version.clone(),
+        releases,
+    };
+    if let (Ok(text), Ok(())) = (serde_json::to_string_pretty(&merged), fs::create_dir_all(MANIFEST_CACHE_DIR)) {
+        _ = write_atomic(&path, text.as_bytes());
+    }
+}
+
+/// Filename -> SHA256 for every release in the cached module manifests.
+fn cached_checksums() -> HashMap<String, String> {
+    let mut checksums = HashMap::new();
+    let Ok(dir) = fs::read_dir(MANIFEST_CACHE_DIR) else {
+        return checksums;
+    };
+    for entry in dir.filter_map(|e| e.ok()) {
+        let Ok(manifest) = fs::read_to_string(entry.path())
+            .map_err(|e| e.to_string())
+            .and_then(|t| {
+                serde_json::from_str::<CloudModuleManifest>(&t).map_err(|e| e.to_string())
+            })
+        else {
+            continue;
+        };
+        for release in manifest.releases {
+            if let Some(name) = release.file.split('/').next_back() {
+                checksums.insert(name.to_string(), release.sha256);
+            }
+        }
+    }
+    checksums
+}
+
+/// The SHA256 recorded for a firmware file when it was downloaded or imported: from its
+/// sidecar, else from `checksums` (filename -> SHA256 from the cached manifests). It is the
+/// checksum of the uncompressed firmware, also for a file stored compressed.
+fn recorded_checksum(path: &Path, checksums: &HashMap<String, String>) -> Option<String> {
+    if let Some(sha256) = read_sidecar(path).ok().and_then(|s| s.sha256) {
+        return Some(sha256);
+    }
+    let name = path.file_name()?.to_string_lossy();
+    checksums.get(uncompressed_name(&name)).cloned()
+}
+
+/// Re-check a firmware file against its recorded checksum right before flashing:
+/// storage can corrupt a file, or someone can edit it, long after it was downloaded.
+/// Files without a recorded checksum pass, their signature still has to.
+fn check_recorded_checksum(file: &FirmwareFile, data: &[u8]) -> Result<(), String> {
+    // the cached manifests only describe the files downloaded into the firmware directory
+    let checksums = if file.path.parent() == Some(Path::new(FIRMWARE_DIR)) {
+        cached_checksums()
+    } else {
+        HashMap::new()
+    };
+    match recorded_checksum(&file.path, &checksums) {
+        Some(sha256) if !verify_sha256(data, &sha256) => Err(format!(
+            "SHA256 of {} does not match the checksum recorded when it was downloaded, \
+             the file is corrupted. Download it again with `go-modules check` or re-import it.",
+            file.file_name()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Check a single firmware file, returning the problems found (empty when the file is good)
+/// and informational notes.
+fn verify_firmware_file(path: &Path, checksums: &HashMap<String, String>) -> (Vec<String>, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut notes = Vec::new();
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let data = match read_firmware(path) {
+        Ok(data) => data,
+        Err(err) => {
+            problems.push(format!("could not read: {err}"));
+            return (problems, notes);
+        }
+    };
+
+    match FirmwareFile::from_path(path, None, None) {
+        Ok(file) => {
+            if file.version.type_name() == "Unknown" {
+                problems.push(format!("unknown module type {}", file.version.as_string()));
+            }
+            match load_firmware(&file) {
+                Ok(_) if file.format == FirmwareFormat::Srec => {
+                    let oversized = std::str::from_utf8(&data)
+                        .ok()
+                        .and_then(|text| srec::parse(text).ok())
+                        .unwrap_or_default()
+                        .iter()
+                        .filter(|r| r.to_bytes().len() > FRAME_RECORD_MAX)
+                        .count();
+                    if oversized > 0 {
+                        notes.push(format!(
+                            "{oversized} records re-blocked to fit the bootloader frame"
+                        ));
+                    }
+                }
+                Ok(_) => (),
+                Err(err) => problems.push(err),
+            }
+        }
+        Err(err) => problems.push(err.trim_start_matches(&format!("{name}: ")).to_string()),
+    }
+
+    match signature_policy(verify_signature(&data, &signature_path(path))) {
+        Ok(None) => notes.push("signed".into()),
+        Ok(Some(reason)) => notes.push(format!("unsigned ({reason})")),
+        Err(reason) => problems.push(format!("signature: {reason}")),
+    }
+
+    match recorded_checksum(path, checksums) {
+        Some(sha256) if !verify_sha256(&data, &sha256) => {
+            problems.push("SHA256 does not match the recorded checksum".into())
+        }
+        Some(_) => notes.push("checksum matches the recorded one".into()),
+        None => notes.push("no recorded checksum".into()),
+    }
+    (problems, notes)
+}
+
+/// Validate firmware files without touching hardware. `target` is `all` for the
+/// firmware directory, a directory or a single file. Returns the per-file verdict lines
+/// and whether every file passed.
+fn verify_firmware(target: &str) -> Result<(Vec<String>, bool), String> {
+    let target = if target == "all" { FIRMWARE_DIR } else { target };
+    let meta = fs::metadata(target).map_err(|e| format!("{target}: {e}"))?;
+    let mut files: Vec<PathBuf> = if meta.is_dir() {
+        fs::read_dir(target)
+            .map_err(|e| format!("{target}: {e}"))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && FirmwareFormat::from_path(p).is_some())
+            .collect()
+    } else {
+        vec![PathBuf::from(target)]
+    };
+    files.sort();
+    if files.is_empty() {
+        return Ok((vec![format!("No firmware files found in {target}")], true));
+    }
+
+    let checksums = cached_checksums();
+    let mut versions: Vec<(FirmwareVersion, String)> = Vec::new();
+    let results: Vec<(String, Vec<String>, Vec<String>)> = files
+        .iter()
+        .map(|p| {
+            let (mut problems, notes) = verify_firmware_file(p, &checksums);
+            let name = p
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if let Ok(file) = FirmwareFile::from_path(p, None, None) {
+                match versions.iter().find(|(v, _)| *v == file.version) {
+                    Some((_, other)) => problems.push(format!("same version as {other}")),
+                    None => versions.push((file.version, name.clone())),
+                }
             }
+            (name, problems, notes)
+        })
+        .collect();
+
+    let name_w = results.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let mut all_ok = true;
+    let mut out = Vec::with_capacity(results.len());
+    for (name, problems, notes) in results {
+        if problems.is_empty() {
+            out.push(format!("{:<name_w$}  OK   {}", name, notes.join(", ")));
+        } else {
+            all_ok = false;
+            out.push(format!("{:<name_w$}  BAD  {}", name, problems.join(", ")));
+        }
+    }
+    Ok((out, all_ok))
+}
+
+/// Write a file so readers see either the old or the complete new contents: write a temporary
+/// file next to it, fsync it, rename it over the target and fsync the directory.
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = temporary_path(path);
+    let result = File::create(&tmp)
+        .and_then(|mut file| file.write_all(data))
+        .and_then(|()| replace_atomic(&tmp, path));
+    if result.is_err() {
+        _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Where `write_atomic` and `replace_atomic` put the new contents of `path` until they are complete.
+/// Not a firmware file name, so a leftover from a crash is never taken for firmware.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".tmp-{}", std::process::id()));
+    PathBuf::from(tmp)
+}
+
+/// Move the finished temporary file `tmp` over `path`, see `write_atomic`, for files written
+/// by something else, like tar.
+fn replace_atomic(tmp: &Path, path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(tmp)?.sync_all()?;
+    fs::rename(tmp, path)?;
+    File::open(dir)?.sync_all()
+}
+
+/// Whether `name`, taken from a manifest, may be stored in the firmware directory: a plain,
+/// uncompressed firmware file name that encodes its version, like 20-10-1-5-0-0-9.srec.
+/// Any other name could replace or delete other files there, `modules.json` for one.
+fn check_firmware_name(name: &str) -> Result<(), String> {
+    let path = Path::new(name);
+    if path.file_name() == Some(name.as_ref())
+        && !is_compressed(path)
+        && FirmwareFormat::from_path(path).is_some()
+        && FirmwareVersion::from_filename(name.to_string()).is_some()
+    {
+        Ok(())
+    } else {
+        Err(format!("{name} is not a firmware file name like 20-10-1-5-0-0-9.srec"))
+    }
+}
+
+/// Store firmware downloaded or imported as `name` in the firmware directory compressed, as
+/// `<name>.gz` with its signature next to it, each with an atomic rename. An uncompressed copy
+/// is replaced, its sidecar moves along. Returns the path of the stored file. `name` must pass
+/// `check_firmware_name`.
+fn store_firmware(name: &str, data: &[u8], signature: Option<&[u8]>) -> io::Result<PathBuf> {
+    check_firmware_name(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    store_compressed(&Path::new(FIRMWARE_DIR).join(name), data, signature)
+}
+
+/// Store `data` as `<plain>.gz` with its signature, replacing the uncompressed file `plain`.
+fn store_compressed(plain: &Path, data: &[u8], signature: Option<&[u8]>) -> io::Result<PathBuf> {
+    let path = compressed_path(plain);
+    write_atomic(&path, &compress_firmware(data)?)?;
+    match signature {
+        Some(sig) => write_atomic(&signature_path(&path), sig)?,
+        None => _ = fs::remove_file(signature_path(&path)),
+    }
+    if plain.is_file() {
+        if !sidecar_path(&path).exists() {
+            _ = fs::rename(sidecar_path(plain), sidecar_path(&path));
         }
+        _ = fs::remove_file(sidecar_path(plain));
+        _ = fs::remove_file(signature_path(plain));
+        fs::remove_file(plain)?;
+    }
+    Ok(path)
+}
+
+/// Whether `path` names a .tar, .tar.gz or .tgz archive rather than a bundle directory.
+fn is_archive(path: &str) -> bool {
+    [".tar", ".tar.gz", ".tgz"].iter().any(|ext| path.ends_with(ext))
+}
+
+/// One firmware file from a bundle, verified and ready to be installed.
+struct BundleFile {
+    name: String,
+    data: Vec<u8>,
+    signature: Option<Vec<u8>>,
+    release: CloudRelease,
+}
+
+/// Install an offline firmware bundle: a directory, or a .tar/.tar.gz archive of one, holding
+/// module manifests (`*.json` in the `CloudModuleManifest` shape) and the firmware files they list,
+/// optionally with `<file>.sig` signatures. Release files are looked up by filename in the bundle root.
+///
+/// Every file is checked against its manifest SHA256 and the signature policy first; only when
+/// all of them pass are they written to the firmware directory, each with an atomic rename. When
+/// writing one fails, the files added before it are removed again; a file that replaced a copy
+/// with the same name stays, it passed the checks.
+/// The manifests are cached like the cloud ones so `verify` can check checksums later.
+fn import_bundle(source: &str) -> Result<Vec<String>, String> {
+    if fs::metadata(source).is_ok_and(|m| m.is_file()) && is_archive(source) {
+        let tmp = env::temp_dir().join(format!("go-modules-import-{}", std::process::id()));
+        fs::create_dir_all(&tmp).map_err(|e| format!("could not create {}: {e}", tmp.display()))?;
+        let result = Command::new("tar")
+            .arg("-xf")
+            .arg(source)
+            .arg("-C")
+            .arg(&tmp)
+            .status()
+            .map_err(|e| format!("could not run tar: {e}"))
+            .and_then(|status| match status.success() {
+                true => import_bundle_dir(&tmp),
+                false => Err(format!("could not extract {source}")),
+            });
+        _ = fs::remove_dir_all(&tmp);
+        return result;
+    }
+    import_bundle_dir(Path::new(source))
+}
+
+fn import_bundle_dir(dir: &Path) -> Result<Vec<String>, String> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("{}: {e}", dir.display()))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    let mut out = Vec::new();
+    let mut problems = Vec::new();
+    let mut manifests = Vec::new();
+    let mut files: Vec<BundleFile> = Vec::new();
+    for path in entries.iter().filter(|p| p.extension().is_some_and(|e| e == "json")) {
+        // <file>.json sidecars of firmware files are not manifests
+        if FirmwareFormat::from_path(&path.with_extension("")).is_some() {
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let Ok(text) = fs::read_to_string(path) else {
+            problems.push(format!("{name}: could not read"));
+            continue;
+        };
+        let manifest: CloudModuleManifest = match serde_json::from_str(&text) {
+            Ok(m) => m,
+            Err(e) => {
+                problems.push(format!("{name}: not a module manifest: {e}"));
+                continue;
+            }
+        };
+        for release in &manifest.releases {
+            let Some(file_name) = release.file_name() else {
+                problems.push(format!("{name}: invalid file path {}, not a firmware file name", release.file));
+                continue;
+            };
+            if files.iter().any(|f| f.name == file_name) {
+                continue;
+            }
+            let Ok(data) = fs::read(dir.join(file_name)) else {
+                out.push(format!("{file_name}: not in bundle, skipped"));
+                continue;
+            };
+            if !verify_sha256(&data, &release.sha256) {
+                problems.push(format!("{file_name}: SHA256 does not match {name}"));
+                continue;
+            }
+            let installed = Path::new(FIRMWARE_DIR).join(file_name);
+            if [compressed_path(&installed), installed]
+                .iter()
+                .any(|path| read_firmware(path).is_ok_and(|existing| existing == data))
+            {
+                out.push(format!("{file_name}: already present, skipped"));
+                continue;
+            }
+            let signature = fs::read(signature_path(&dir.join(file_name))).ok();
+            let signed = match &signature {
+                Some(sig) => verify_signature_bytes(&data, sig),
+                None => Err("not signed".into()),
+            };
+            match signature_policy(signed) {
+                Ok(None) => (),
+                Ok(Some(reason)) => out.push(format!("{file_name}: accepted unsigned ({reason})")),
+                Err(reason) => {
+                    problems.push(format!("{file_name}: signature: {reason}"));
+                    continue;
+                }
+            }
+            files.push(BundleFile {
+                name: file_name.to_string(),
+                data,
+                signature,
+                release: release.clone(),
+            });
+        }
+        manifests.push(manifest);
+    }
+
+    if manifests.is_empty() && problems.is_empty() {
+        return Err(format!("no module manifests found in {}", dir.display()));
+    }
+    if !problems.is_empty() {
+        problems.push("Bundle rejected, nothing was installed.".into());
+        return Err(problems.join("\n"));
+    }
+
+    let total: u64 = files.iter().map(|f| f.data.len() as u64).sum();
+    fs::create_dir_all(FIRMWARE_DIR)
+        .map_err(|e| format!("Could not create firmware directory {FIRMWARE_DIR}: {e}"))?;
+    if !has_room_for(total) {
+        return Err(format!(
+            "not enough free space in {FIRMWARE_DIR} for {} KiB, nothing was installed",
+            total.div_ceil(1024)
+        ));
+    }
+    // files that weren't in the library before, removed again when a later one can't be written
+    let mut added: Vec<PathBuf> = Vec::new();
+    for file in &files {
+        let installed = Path::new(FIRMWARE_DIR).join(&file.name);
+        let existed = installed.is_file() || compressed_path(&installed).is_file();
+        let path = match store_firmware(&file.name, &file.data, file.signature.as_deref()) {
+            Ok(path) => path,
+            Err(e) => {
+                for path in &added {
+                    _ = fs::remove_file(sidecar_path(path));
+                    _ = fs::remove_file(signature_path(path));
+                    _ = fs::remove_file(path);
+                }
+                return Err(format!(
+                    "could not install {}: {e}, the files added before it were removed again",
+                    file.name
+                ));
+            }
+        };
+        if !existed {
+            added.push(path.clone());
+        }
+        write_sidecar(&path, "bundle", &file.release);
+        out.push(format!("{}: added", file.name));
+    }
+    for manifest in &manifests {
+        cache_manifest(manifest);
+    }
+    out.push(format!("{} files added", files.len()));
+    Ok(out)
+}
+
+/// Write the firmware library as a bundle that `import_bundle` accepts: every firmware file with
+/// its signature and sidecar, plus one manifest per hardware. Release dates and changelogs come
+/// from the cached cloud manifest when it knows the file. A target ending in .tar, .tar.gz or .tgz
+/// is written as an archive, anything else as a directory.
+fn export_bundle(target: &str) -> Result<Vec<String>, String> {
+    let groups = firmware_by_hardware(read_firmware_dir());
+    if groups.is_empty() {
+        return Err(format!("No firmware files in {FIRMWARE_DIR}"));
+    }
+    let archive = is_archive(target);
+    let dir = if archive {
+        env::temp_dir().join(format!("go-modules-export-{}", std::process::id()))
+    } else {
+        PathBuf::from(target)
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("could not create {}: {e}", dir.display()))?;
 
-        if needs_download {
-            match client
-                .get(format!("{}/{}", CLOUD_BASE_URL, latest.file))
-                .send()
-                .await
-            {
-                Ok(resp) => match resp.bytes().await {
-                    Ok(data) => {
-                        if !verify_sha256(&data, &latest.sha256) {
-                            status = "checksum verification failed".into();
-                        } else if let Err(e) = fs::write(&local_path, &data) {
-                            status = format!("could not save {filename}: {e}");
-                        } else if status.is_empty() {
-                            status = "downloaded".into();
-                        } else {
-                            status = "re-downloaded".into();
-                        }
+    let cached: Vec<CloudModuleManifest> = fs::read_dir(MANIFEST_CACHE_DIR)
+        .map(|d| {
+            d.filter_map(|e| e.ok())
+                .filter_map(|e| fs::read_to_string(e.path()).ok())
+                .filter_map(|t| serde_json::from_str(&t).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut out = Vec::new();
+    let result = (|| {
+        for group in &groups {
+            let first = &group[0].version;
+            let mut manifest = CloudModuleManifest {
+                name: first.type_name().to_string(),
+                hardware_version: hardware_string(first),
+                releases: Vec::new(),
+            };
+            for file in group {
+                // bundles hold the firmware uncompressed, like the cloud
+                let name = uncompressed_name(&file.file_name()).to_string();
+                let data = read_firmware(&file.path).map_err(|e| format!("could not read {name}: {e}"))?;
+                let known = cached.iter().find_map(|m| {
+                    m.releases
+                        .iter()
+                        .find(|r| r.file.split('/').next_back() == Some(name.as_str()))
+                        .map(|r| (m, r))
+                });
+                if let Some((m, _)) = known {
+                    manifest.name = m.name.clone();
+                    manifest.hardware_version = m.hardware_version.clone();
+                }
+                manifest.releases.push(CloudRelease {
+                    sw_version: firmware_version_string(&file.version),
+                    file: name.clone(),
+                    date: known.map(|(_, r)| r.date.clone()).unwrap_or_default(),
+                    sha256: hex::encode(Sha256::digest(&data)),
+                    changelog: known.map(|(_, r)| r.changelog.clone()).unwrap_or_default(),
+                    compatible_revisions: file.compatible_revisions.map(|r| r.to_string()),
+                });
+                let bundled = dir.join(&name);
+                write_atomic(&bundled, &data).map_err(|e| format!("could not write {name}: {e}"))?;
+                for (extra, target) in [
+                    (signature_path(&file.path), signature_path(&bundled)),
+                    (sidecar_path(&file.path), sidecar_path(&bundled)),
+                ] {
+                    if let Ok(data) = fs::read(&extra) {
+                        write_atomic(&target, &data)
+                            .map_err(|e| format!("could not write {}: {e}", target.display()))?;
                     }
-                    Err(e) => status = format!("download failed: {e}"),
-                },
-                Err(e) => status = format!("download failed: {e}"),
+                }
+                out.push(format!("{name}: exported"));
             }
+            let json = serde_json::to_string_pretty(&manifest)
+                .map_err(|e| format!("could not serialize manifest: {e}"))?;
+            write_atomic(&dir.join(format!("{}.json", hardware_string(first))), json.as_bytes())
+                .map_err(|e| format!("could not write manifest: {e}"))?;
         }
+        if archive {
+            // tar writes a temporary archive, a half written bundle must not look complete
+            let tmp = temporary_path(Path::new(target));
+            let status = Command::new("tar")
+                .arg(if target.ends_with(".tar") { "-cf" } else { "-czf" })
+                .arg(&tmp)
+                .arg("-C")
+                .arg(&dir)
+                .arg(".")
+                .status()
+                .map_err(|e| format!("could not run tar: {e}"))?;
+            if !status.success() {
+                _ = fs::remove_file(&tmp);
+                return Err(format!("could not create {target}"));
+            }
+            replace_atomic(&tmp, Path::new(target)).map_err(|e| {
+                _ = fs::remove_file(&tmp);
+                format!("could not create {target}: {e}")
+            })?;
+        }
+        Ok(())
+    })();
+    if archive {
+        _ = fs::remove_dir_all(&dir);
+    }
+    result?;
+    out.push(format!("Bundle written to {target}"));
+    Ok(out)
+}
 
-        entries.push(CheckEntry {
-            name: sub_manifest.name,
-            hw: sub_manifest.hardware_version,
-            sw: latest.sw_version.clone(),
-            status,
-            released: Some(latest.date.clone()),
-            changelog: Some(latest.changelog.clone()),
+/// Free bytes on the filesystem holding `path` according to `df`, None when unknown.
+fn free_space(path: &str) -> Option<u64> {
+    let output = Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let available: u64 = text.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available * 1024)
+}
+
+/// Whether `size` more bytes fit in the firmware directory while keeping
+/// `FIRMWARE_MIN_FREE_SPACE` free. Assumes yes when the free space is unknown.
+fn has_room_for(size: u64) -> bool {
+    free_space(FIRMWARE_DIR).is_none_or(|free| free >= size + FIRMWARE_MIN_FREE_SPACE)
+}
+
+/// Firmware per slot as recorded in modules.json by the last scan, read without touching hardware.
+fn installed_firmware() -> Vec<(u8, FirmwareVersion)> {
+    recorded_firmware(|slot| Some(slot.firmware))
+}
+
+/// Firmware per slot to roll back to, as recorded in modules.json by the last flash.
+fn previous_firmware() -> Vec<(u8, FirmwareVersion)> {
+    recorded_firmware(|slot| slot.previous_firmware)
+}
+
+fn recorded_firmware(field: impl Fn(SlotEntry) -> Option<String>) -> Vec<(u8, FirmwareVersion)> {
+    fs::read_to_string(MODULES_JSON_PATH)
+        .ok()
+        .and_then(|s| serde_json::from_str::<ModulesJson>(&s).ok())
+        .map(|doc| {
+            doc.slots
+                .into_iter()
+                .filter_map(|slot| {
+                    let number = slot.slot;
+                    Some((number, FirmwareVersion::from_filename(field(slot)?)?))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The firmware library grouped by hardware, newest version first in every group.
+fn firmware_by_hardware(files: Vec<FirmwareFile>) -> Vec<Vec<FirmwareFile>> {
+    let mut groups: Vec<Vec<FirmwareFile>> = Vec::new();
+    for file in files {
+        match groups
+            .iter_mut()
+            .find(|g| g[0].version.get_hardware() == file.version.get_hardware())
+        {
+            Some(group) => group.push(file),
+            None => groups.push(vec![file]),
+        }
+    }
+    for group in groups.iter_mut() {
+        group.sort_by(|a, b| {
+            b.version
+                .get_software()
+                .cmp(a.version.get_software())
+                .then_with(|| a.file_name().cmp(&b.file_name()))
         });
     }
+    groups.sort_by(|a, b| a[0].version.get_hardware().cmp(b[0].version.get_hardware()));
+    groups
+}
 
-    // Compute column widths for space alignment.
-    let name_w = entries.iter().map(|e| e.name.len()).max().unwrap_or(0);
-    let hw_w = entries.iter().map(|e| e.hw.len()).max().unwrap_or(0);
-    let sw_w = entries.iter().map(|e| e.sw.len()).max().unwrap_or(0);
+/// Delete a firmware file together with its sidecar and signature, returning the bytes freed.
+fn remove_firmware_file(file: &FirmwareFile) -> Result<u64, String> {
+    let size = fs::metadata(&file.path).map(|m| m.len()).unwrap_or(0);
+    fs::remove_file(&file.path).map_err(|e| format!("could not remove {}: {e}", file.file_name()))?;
+    _ = fs::remove_file(sidecar_path(&file.path));
+    _ = fs::remove_file(signature_path(&file.path));
+    Ok(size)
+}
 
-    let mut out = Vec::with_capacity(entries.len() * if verbose { 3 } else { 1 } + 2);
-    out.push(format!(
-        "Cloud manifest last updated: {}",
-        main_manifest.updated
-    ));
+/// `firmware list`: the library per hardware with size, newest, installed and rollback markers.
+fn firmware_list() -> Vec<String> {
+    let installed = installed_firmware();
+    let previous = previous_firmware();
+    let groups = firmware_by_hardware(read_firmware_dir());
+    if groups.is_empty() {
+        return vec![format!("No firmware files in {FIRMWARE_DIR}")];
+    }
+    let name_w = groups
+        .iter()
+        .flatten()
+        .map(|f| f.file_name().len())
+        .max()
+        .unwrap_or(0);
+    let mut total: u64 = 0;
+    let mut count = 0;
+    let mut out = Vec::new();
+    for group in &groups {
+        let first = &group[0].version;
+        out.push(format!("{}  {}", hardware_string(first), first.type_name()));
+        for file in group {
+            let size = fs::metadata(&file.path).map(|m| m.len()).unwrap_or(0);
+            total += size;
+            count += 1;
+            let mut marks = Vec::new();
+            if file.version.get_software() == first.get_software() {
+                marks.push("newest".to_string());
+            }
+            let slots: Vec<String> = installed
+                .iter()
+                .filter(|(_, fw)| file.provides(fw))
+                .map(|(slot, _)| slot.to_string())
+                .collect();
+            if !slots.is_empty() {
+                marks.push(format!("installed in slot {}", slots.join(", ")));
+            }
+            let slots: Vec<String> = previous
+                .iter()
+                .filter(|(_, fw)| file.provides(fw))
+                .map(|(slot, _)| slot.to_string())
+                .collect();
+            if !slots.is_empty() {
+                marks.push(format!("rollback for slot {}", slots.join(", ")));
+            }
+            if !file.origin.is_empty() {
+                marks.push(format!("from {}", file.origin));
+            }
+            if let Some(date) = &file.released {
+                marks.push(format!("released {date}"));
+            }
+            out.push(
+                format!(
+                    "  {:<name_w$}  {:>6} KiB  {}",
+                    file.file_name(),
+                    size.div_ceil(1024),
+                    marks.join(", ")
+                )
+                .trim_end()
+                .to_string(),
+            );
+        }
+    }
     out.push(String::new());
+    out.push(format!("{count} files, {} KiB", total.div_ceil(1024)));
+    let ignored = ignored_firmware_report();
+    if !ignored.is_empty() {
+        out.push(String::new());
+        out.extend(ignored);
+    }
+    out
+}
 
-    for e in &entries {
-        let mut line = String::new();
-        let _ = write!(line, "{:<name_w$}", e.name, name_w = name_w);
-        if hw_w > 0 {
-            let _ = write!(line, "  HW {:<hw_w$}", e.hw, hw_w = hw_w);
+/// `firmware prune --keep n`: delete all but the `keep` newest versions per hardware.
+/// Versions installed on a module, kept for its rollback or that its pin would
+/// update it to are never deleted.
+fn firmware_prune(keep: usize) -> Vec<String> {
+    let library = read_source_dir(Path::new(FIRMWARE_DIR));
+    let mut installed = installed_firmware();
+    // The newest version a pinned slot may update to is what `update` will pick, keep it too.
+    let pinned: Vec<(u8, FirmwareVersion)> = installed
+        .iter()
+        .filter(|(slot, fw)| pin_policy().pin_for(Some(*slot), fw).is_some())
+        .filter_map(|(slot, fw)| {
+            library
+                .iter()
+                .filter(|f| f.fit(fw).fits())
+                .filter(|f| pin_policy().allows(Some(*slot), &f.version))
+                .max_by(|a, b| a.version.get_software().cmp(b.version.get_software()))
+                .map(|f| (*slot, f.version))
+        })
+        .collect();
+    installed.extend(pinned);
+    installed.extend(previous_firmware());
+    let mut out = Vec::new();
+    let mut freed: u64 = 0;
+    for group in firmware_by_hardware(library) {
+        let mut versions: Vec<FirmwareVersion> = Vec::new();
+        for file in &group {
+            if !versions.contains(&file.version) {
+                versions.push(file.version);
+            }
         }
-        if sw_w > 0 {
-            let _ = write!(
-                line,
-                "  v{:<sw_w$}",
-                if e.sw.is_empty() { "" } else { &e.sw },
-                sw_w = sw_w
-            );
+        for file in group.iter().filter(|f| {
+            versions.iter().position(|v| *v == f.version).unwrap_or(0) >= keep
+                && !installed.iter().any(|(_, fw)| f.provides(fw))
+        }) {
+            match remove_firmware_file(file) {
+                Ok(size) => {
+                    freed += size;
+                    out.push(format!("removed {}", file.file_name()));
+                }
+                Err(err) => out.push(err),
+            }
         }
-        let _ = write!(line, "  {}", e.status);
-        out.push(line);
-
-        if verbose {
-            if let Some(r) = &e.released {
-                out.push(format!("  Released: {r}"));
+    }
+    for (name, size) in stale_partial_downloads() {
+        match fs::remove_file(Path::new(FIRMWARE_DIR).join(&name)) {
+            Ok(()) => {
+                freed += size;
+                out.push(format!("removed {name}, an interrupted download"));
             }
-            if let Some(c) = &e.changelog {
-                out.push(format!("  Changes:  {c}"));
+            Err(e) => out.push(format!("could not remove {name}: {e}")),
+        }
+    }
+    if out.is_empty() {
+        out.push("Nothing to prune.".into());
+    } else {
+        out.push(format!("{} KiB freed", freed.div_ceil(1024)));
+    }
+    out
+}
+
+/// The `<file>.part` files interrupted downloads left in the firmware directory, with their
+/// size. A download in progress writes to its file at least every `STALL_TIMEOUT` or is
+/// aborted, so one that hasn't changed for longer is not being written anymore.
+fn stale_partial_downloads() -> Vec<(String, u64)> {
+    let Ok(entries) = fs::read_dir(FIRMWARE_DIR) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let meta = entry.metadata().ok()?;
+            let idle = meta.modified().ok()?.elapsed().unwrap_or_default();
+            (name.ends_with(".part") && meta.is_file() && idle > STALL_TIMEOUT).then_some((name, meta.len()))
+        })
+        .collect()
+}
+
+/// `firmware compress`: store the uncompressed files in the firmware directory compressed, like
+/// `check` and `import` do, so a library downloaded by an older go-modules takes less space.
+fn firmware_compress() -> Vec<String> {
+    let mut out = Vec::new();
+    let mut saved: u64 = 0;
+    let library = read_source_dir(Path::new(FIRMWARE_DIR));
+    for file in library.iter().filter(|f| !is_compressed(&f.path)) {
+        let name = file.file_name();
+        let signature = fs::read(signature_path(&file.path)).ok();
+        let compressed = fs::read(&file.path)
+            .and_then(|data| Ok((data.len() as u64, store_compressed(&file.path, &data, signature.as_deref())?)));
+        match compressed {
+            Ok((size, path)) => {
+                let stored = fs::metadata(&path).map(|m| m.len()).unwrap_or(size);
+                saved += size.saturating_sub(stored);
+                out.push(format!("compressed {name}"));
             }
+            Err(e) => out.push(format!("could not compress {name}: {e}")),
         }
     }
+    if out.is_empty() {
+        out.push("Nothing to compress.".into());
+    } else {
+        out.push(format!("{} KiB freed", saved.div_ceil(1024)));
+    }
+    out
+}
 
+/// `firmware info <file>`: everything known about a firmware file, including the release
+/// metadata recorded when it was downloaded or imported.
+fn firmware_info(name: &str) -> Result<Vec<String>, String> {
+    let file = read_firmware_dir()
+        .into_iter()
+        .find(|f| f.is_named(name))
+        .ok_or_else(|| format!("{name} is not a firmware file in the catalogue"))?;
+    let sidecar = read_sidecar(&file.path)?;
+    let data = read_firmware(&file.path).map_err(|e| format!("could not read {name}: {e}"))?;
+    let actual = hex::encode(Sha256::digest(&data));
+    let unknown = || "unknown".to_string();
+    let mut out = vec![
+        format!("File:        {}", file.path.display()),
+        format!(
+            "Version:     {} ({}, sw {})",
+            file.version.as_string(),
+            file.version.type_name(),
+            firmware_version_string(&file.version)
+        ),
+        format!(
+            "Size:        {} KiB{}",
+            (data.len() as u64).div_ceil(1024),
+            match fs::metadata(&file.path) {
+                Ok(m) if is_compressed(&file.path) => format!(", {} KiB compressed", m.len().div_ceil(1024)),
+                _ => String::new(),
+            }
+        ),
+        format!(
+            "Revisions:   {}",
+            match file.compatible_revisions {
+                Some(range) => format!("{range} (declared by the release)"),
+                None => file.version.get_hardware()[3].to_string(),
+            }
+        ),
+        format!("Source:      {}", sidecar.source.unwrap_or_else(unknown)),
+        format!("Released:    {}", sidecar.date.filter(|d| !d.is_empty()).unwrap_or_else(unknown)),
+        format!("Downloaded:  {}", sidecar.downloaded.unwrap_or_else(unknown)),
+    ];
+    out.push(match sidecar.sha256 {
+        Some(recorded) if recorded.eq_ignore_ascii_case(&actual) => format!("SHA256:      {actual} (matches)"),
+        Some(recorded) => format!("SHA256:      {actual} (recorded {recorded}, DOES NOT MATCH)"),
+        None => format!("SHA256:      {actual}"),
+    });
+    if let Some(changelog) = sidecar.changelog.filter(|c| !c.is_empty()) {
+        out.push(format!("Changes:     {changelog}"));
+    }
     Ok(out)
 }
 
+/// `firmware remove <file>`: delete one file from the library. A version that is
+/// installed on a module is only removed with `force`.
+fn firmware_remove(name: &str, force: bool) -> Result<Vec<String>, String> {
+    let file = read_source_dir(Path::new(FIRMWARE_DIR))
+        .into_iter()
+        .find(|f| f.is_named(name))
+        .ok_or_else(|| format!("{name} is not a firmware file in {FIRMWARE_DIR}"))?;
+    let slots: Vec<String> = installed_firmware()
+        .iter()
+        .filter(|(_, fw)| file.provides(fw))
+        .map(|(slot, _)| slot.to_string())
+        .collect();
+    if !slots.is_empty() && !force {
+        return Err(format!(
+            "{name} is installed in slot {}, use --force to remove it anyway",
+            slots.join(", ")
+        ));
+    }
+    let slots: Vec<String> = previous_firmware()
+        .iter()
+        .filter(|(_, fw)| file.provides(fw))
+        .map(|(slot, _)| slot.to_string())
+        .collect();
+    if !slots.is_empty() && !force {
+        return Err(format!(
+            "{name} is the rollback firmware for slot {}, use --force to remove it anyway",
+            slots.join(", ")
+        ));
+    }
+    let size = remove_firmware_file(&file)?;
+    Ok(vec![format!("removed {name}, {} KiB freed", size.div_ceil(1024))])
+}
+
 /// get the current modules in the controller
 async fn get_modules(controller: &ControllerTypes) -> Vec<Module> {
     let mut modules = Vec::with_capacity(8);
@@ -1952,7 +4149,7 @@ fn save_modules(modules: Vec<Option<Module>>, controller: &ControllerTypes) -> V
         controller: controller_schema_name(controller).to_string(),
         slots: Vec::new(),
     };
-    let path = "/lib/firmware/gocontroll/modules.json";
+    let path = MODULES_JSON_PATH;
     let mut doc: ModulesJson = match fs::read_to_string(path) {
         Ok(s) => match serde_json::from_str::<ModulesJson>(&s) {
             Ok(d) => d,
@@ -1966,7 +4163,7 @@ fn save_modules(modules: Vec<Option<Module>>, controller: &ControllerTypes) -> V
                     "modules.json failed to parse ({e}); backing up to {bak} \
                      and starting from an empty doc"
                 );
-                let _ = fs::copy(path, &bak);
+                let _ = fs::read(path).and_then(|data| write_atomic(Path::new(&bak), &data));
                 empty_doc()
             }
         },
@@ -2048,6 +4245,17 @@ fn save_modules(modules: Vec<Option<Module>>, controller: &ControllerTypes) -> V
                     // (older schema, hand-edited file) is treated as compatible
                     // so user-edited config is not clobbered.
                     let type_changed = matches!(existing.module_type, Some(prev) if prev != detected_type);
+                    // Remember the firmware being replaced so it can be rolled back to,
+                    // as long as it was built for the same hardware.
+                    if existing.firmware != firmware {
+                        let old = FirmwareVersion::from_filename(existing.firmware.clone());
+                        existing.previous_firmware = match old {
+                            Some(old) if old.get_hardware() == module.firmware.get_hardware() => {
+                                Some(existing.firmware.clone())
+                            }
+                            _ => None,
+                        };
+                    }
                     existing.module_type = Some(detected_type);
                     existing.article_number = Some(article_number);
                     existing.hardware_version = Some(hardware_version);
@@ -2083,6 +4291,7 @@ fn save_modules(modules: Vec<Option<Module>>, controller: &ControllerTypes) -> V
                         hardware_version: Some(hardware_version),
                         firmware_version: Some(firmware_version),
                         label: None,
+                        previous_firmware: None,
                         module: detected_type.default_module(),
                         channels: detected_type.default_channels(),
                     });
@@ -2110,7 +4319,8 @@ fn save_modules(modules: Vec<Option<Module>>, controller: &ControllerTypes) -> V
     }
     match serde_json::to_string_pretty(&doc) {
         Ok(json) => {
-            if fs::write("/lib/firmware/gocontroll/modules.json", json).is_err() {
+            // go-hardware-driver reads it at any time, it must never see a partial file
+            if write_atomic(Path::new(MODULES_JSON_PATH), json.as_bytes()).is_err() {
                 eprintln!(
                     "Could not save module layout to /lib/firmware/gocontroll/modules.json"
                 );
@@ -2170,13 +4380,11 @@ fn write_legacy_modules_txt(
                     qr_back[i] = module.qr_back.to_string();
                 }
             }
-            None if full_scan => {
-                if idx < slot_count {
-                    firmware[idx] = String::new();
-                    manufacturer[idx] = "0".to_string();
-                    qr_front[idx] = "0".to_string();
-                    qr_back[idx] = "0".to_string();
-                }
+            None if full_scan && idx < slot_count => {
+                firmware[idx] = String::new();
+                manufacturer[idx] = "0".to_string();
+                qr_front[idx] = "0".to_string();
+                qr_back[idx] = "0".to_string();
             }
             None => {}
         }
@@ -2192,7 +4400,7 @@ fn write_legacy_modules_txt(
         qr_front.join(":"),
         qr_back.join(":"),
     );
-    if fs::write("/usr/module-firmware/modules.txt", text_content).is_err() {
+    if write_atomic(Path::new("/usr/module-firmware/modules.txt"), text_content.as_bytes()).is_err() {
         eprintln!("Could not save module layout to /usr/module-firmware/modules.txt");
     }
 }
@@ -2201,18 +4409,21 @@ fn write_legacy_modules_txt(
 /// human-readable status lines for the result view.
 async fn update_one_module(
     module: Module,
-    available_firmwares: &[FirmwareVersion],
+    available_firmwares: &[FirmwareFile],
     multi_progress: MultiProgress,
     style: ProgressStyle,
     controller: ControllerTypes,
 ) -> (Option<Module>, Vec<String>) {
+    let note = latest_update_for(&module, available_firmwares)
+        .map(|file| file.cross_revision_note(&module.firmware))
+        .unwrap_or_default();
     match module
         .update_module(available_firmwares, multi_progress, style)
         .await
     {
         Ok(Ok(module)) => {
             let line = format!(
-                "Successfully updated slot {} to {}",
+                "Successfully updated slot {} to {}{note}",
                 module.slot,
                 module.firmware.as_string()
             );
@@ -2229,65 +4440,212 @@ async fn update_one_module(
             (None, vec![format!("Update failed on slot {slot}")])
         }
         Ok(Err(module)) => {
-            let line = format!(
+            let mut line = format!(
                 "Update failed, no update available for slot {}: {}",
                 module.slot,
                 module.firmware.as_string()
             );
+            if let Some(pin) = pin_policy().pin_for(Some(module.slot), &module.firmware) {
+                let _ = write!(line, " ({pin})");
+            }
             (Some(module), vec![line])
         }
     }
 }
 
-/// Update every module in parallel. Returns status lines for the result view.
-async fn update_all_modules(
-    modules: Vec<Module>,
-    available_firmwares: &[FirmwareVersion],
+/// Flash every module with its file in parallel, one task per slot, wiping a module
+/// whose upload failed halfway. Returns the modules that now run their new firmware
+/// and status lines for the ones that failed.
+async fn flash_all(
+    jobs: Vec<(Module, FirmwareFile)>,
     multi_progress: &MultiProgress,
     style: &ProgressStyle,
-    controller: ControllerTypes,
-) -> Vec<String> {
-    let mut upload_results = Vec::with_capacity(modules.len());
-    let mut new_modules = Vec::with_capacity(modules.len());
+) -> (Vec<Module>, Vec<String>) {
+    let mut new_modules = Vec::with_capacity(jobs.len());
     let mut lines: Vec<String> = Vec::new();
     let mut set = JoinSet::new();
-    let shared_firmwares: Arc<[FirmwareVersion]> = Arc::from(available_firmwares);
-    for module in modules {
-        let firmwares = Arc::clone(&shared_firmwares);
+    for (mut module, file) in jobs {
         let multi_progress = multi_progress.clone();
         let style = style.clone();
-        set.spawn(
-            async move { module.update_module(&firmwares, multi_progress, style).await },
-        );
-    }
-    for _ in 0..set.len() {
-        upload_results.push(set.join_next().await.unwrap().unwrap());
+        set.spawn(async move {
+            println!(
+                "updating slot {} from {} to {}{}",
+                module.slot,
+                module.firmware.as_string(),
+                file.version.as_string(),
+                file.cross_revision_note(&module.firmware)
+            );
+            let result = module.overwrite_module(&file, multi_progress, style).await;
+            match result {
+                Ok(()) => module.firmware = file.version.flashed_onto(&module.firmware),
+                Err(UploadError::FirmwareCorrupted(slot)) => {
+                    eprintln!("firmware upload critically failed on slot {slot}, wiping firmware...");
+                    module.wipe_module_error().await;
+                }
+                Err(UploadError::FirmwareUntouched(_)) => (),
+            }
+            (module, result)
+        });
     }
-    for result in upload_results {
-        match result {
-            Ok(Ok(module)) => new_modules.push(Some(module)),
-            Err(UploadError::FirmwareCorrupted(slot)) => {
+    while let Some(joined) = set.join_next().await {
+        match joined.unwrap() {
+            (module, Ok(())) => new_modules.push(module),
+            (_, Err(UploadError::FirmwareCorrupted(slot))) => {
                 lines.push(format!("Update failed, firmware is corrupted on slot {slot}"));
             }
-            Err(UploadError::FirmwareUntouched(slot)) => {
+            (_, Err(UploadError::FirmwareUntouched(slot))) => {
                 lines.push(format!("Update failed on slot {slot}"));
             }
-            Ok(Err(_)) => (), //no new firmwares available
         }
     }
+    new_modules.sort_by_key(|m| m.slot);
+    (new_modules, lines)
+}
+
+/// Update every module in parallel. Returns status lines for the result view.
+async fn update_all_modules(
+    modules: Vec<Module>,
+    available_firmwares: &[FirmwareFile],
+    multi_progress: &MultiProgress,
+    style: &ProgressStyle,
+    controller: ControllerTypes,
+) -> Vec<String> {
+    let jobs: Vec<(Module, FirmwareFile)> = modules
+        .into_iter()
+        .filter_map(|module| {
+            let file = latest_update_for(&module, available_firmwares)?.clone();
+            Some((module, file))
+        })
+        .collect();
+    let notes: HashMap<u8, String> = jobs
+        .iter()
+        .map(|(module, file)| (module.slot, file.cross_revision_note(&module.firmware)))
+        .collect();
+    let (new_modules, mut lines) = flash_all(jobs, multi_progress, style).await;
     if !new_modules.is_empty() {
         lines.push("Successfully updated:".into());
-        for module in &new_modules {
-            let m = module.as_ref().unwrap();
-            lines.push(format!("slot {} to {}", m.slot, m.firmware.as_string()));
+        for m in &new_modules {
+            lines.push(format!(
+                "slot {} to {}{}",
+                m.slot,
+                m.firmware.as_string(),
+                notes.get(&m.slot).map(String::as_str).unwrap_or_default()
+            ));
         }
     } else if lines.is_empty() {
         lines.push("No updates found for the modules in this controller.".into());
     }
-    save_modules(new_modules, &controller);
+    save_modules(new_modules.into_iter().map(Some).collect(), &controller);
     lines
 }
 
+/// `overwrite --type <module type> <firmware>` / `overwrite --article <n> <firmware>`:
+/// flash the same firmware onto every matching module. `firmware` is resolved per
+/// module like a selector (`latest`, `1.2.3`, …) or a full version/filename, which
+/// must be built for the module's hardware. The uploads run concurrently through
+/// `flash_all`, the plan is returned ahead of the results; downgrades need
+/// `--allow-downgrade` and are noted for every slot they were flashed on.
+async fn run_batch_overwrite(
+    modules: Vec<Module>,
+    available_firmwares: &[FirmwareFile],
+    multi_progress: &MultiProgress,
+    style: &ProgressStyle,
+    controller: ControllerTypes,
+    firmware_arg: Option<String>,
+) -> (Vec<Module>, Vec<String>) {
+    let Some(arg) = firmware_arg else {
+        return (modules, vec![format!("Missing firmware for the batch overwrite\n{}", USAGE)]);
+    };
+    let (description, selected): (String, Vec<Module>) = if let Some(name) = command_line().value("--type") {
+        let Ok(module_type) = serde_json::from_value::<ModuleType>(Value::String(name.to_string())) else {
+            return (modules, vec![format!("Unknown module type: {name}")]);
+        };
+        let (selected, _) = modules
+            .into_iter()
+            .partition(|m| ModuleType::from_firmware(&m.firmware) == Some(module_type));
+        (format!("module type {name}"), selected)
+    } else {
+        let article = command_line().value("--article").unwrap_or_default();
+        let Ok(number) = article.parse::<u32>() else {
+            return (modules, vec![format!("Invalid article number: {article}")]);
+        };
+        let (selected, _) = modules
+            .into_iter()
+            .partition(|m| article_number_from_firmware(&m.firmware) == number);
+        (format!("article {number}"), selected)
+    };
+    if selected.is_empty() {
+        return (Vec::new(), vec![format!("No modules with {description} found")]);
+    }
+
+    let allow_downgrade = command_line().flag("--allow-downgrade");
+    let mut lines = vec![format!("Plan for {description}, {arg}:")];
+    let mut jobs = Vec::new();
+    for module in selected {
+        let resolved = resolve_selector(&arg, &module, available_firmwares).unwrap_or_else(|| {
+            let version = FirmwareVersion::from_filename(arg.clone())
+                .ok_or_else(|| format!("Invalid firmware entered: {arg}"))?;
+            let file = available_firmwares
+                .iter()
+                .find(|f| f.version == version)
+                .ok_or_else(|| format!("{}{} does not exist", FIRMWARE_DIR, arg))?;
+            if !file.fit(&module.firmware).fits() {
+                return Err(format!(
+                    "{file} is built for hardware {}, not compatible with {}",
+                    hardware_string(&file.version),
+                    hardware_string(&module.firmware)
+                ));
+            }
+            Ok(file.clone())
+        });
+        let step = match resolved {
+            Ok(file) => match downgrade_kind(&module.firmware, &file.version, arg == "installed") {
+                Some(kind) if !allow_downgrade => Err(format!("{kind} needs --allow-downgrade")),
+                kind => Ok((file, kind)),
+            },
+            Err(e) => Err(e),
+        };
+        match step {
+            Ok((file, kind)) => {
+                lines.push(format!(
+                    "  slot {}: {} -> {}{}",
+                    module.slot,
+                    firmware_version_string(&module.firmware),
+                    file.file_name(),
+                    file.cross_revision_note(&module.firmware)
+                ));
+                jobs.push((module, file, kind));
+            }
+            Err(e) => lines.push(format!("  slot {}: skipped, {e}", module.slot)),
+        }
+    }
+    if jobs.is_empty() {
+        return (Vec::new(), lines);
+    }
+
+    // what each explicitly allowed downgrade or reflash replaces, reported once it worked
+    let allowed: HashMap<u8, (&str, FirmwareVersion, FirmwareVersion)> = jobs
+        .iter()
+        .filter_map(|(module, file, kind)| Some((module.slot, ((*kind)?, module.firmware, file.version))))
+        .collect();
+    let jobs = jobs.into_iter().map(|(module, file, _)| (module, file)).collect();
+    let (new_modules, errors) = flash_all(jobs, multi_progress, style).await;
+    for m in &new_modules {
+        lines.push(format!("Successfully updated slot {} to {}", m.slot, m.firmware.as_string()));
+        if let Some((kind, from, to)) = allowed.get(&m.slot) {
+            lines.push(format!(
+                "Slot {}: {kind} from {} to {} was explicitly allowed",
+                m.slot,
+                firmware_version_string(from),
+                firmware_version_string(to)
+            ));
+        }
+    }
+    lines.extend(errors);
+    save_modules(new_modules.into_iter().map(Some).collect(), &controller);
+    (Vec::new(), lines)
+}
+
 /// Reset the screen so the next view starts at the top, then re-print the
 /// banner and optional subtitle. Called between menu transitions so output
 /// stacks predictably. Pass an empty string to skip the subtitle line.
@@ -2349,19 +4707,222 @@ fn stop_service(name: &str) {
     _ = Command::new("systemctl").arg("stop").arg(name).status();
 }
 
-fn read_firmware_dir() -> Vec<FirmwareVersion> {
-    let dir = match fs::read_dir(FIRMWARE_DIR) {
+/// The firmware catalogue: the files of every local directory source, in source order.
+/// When several directories hold the same version the first one wins, so a directory
+/// listed before the firmware directory overrides downloaded files.
+fn read_firmware_dir() -> Vec<FirmwareFile> {
+    let mut files: Vec<FirmwareFile> = Vec::new();
+    for source in firmware_sources() {
+        let FirmwareSource::LocalDir { path } = source else {
+            continue;
+        };
+        for mut file in read_source_dir(path) {
+            if files.iter().any(|f| f.version == file.version) {
+                continue;
+            }
+            if !source.is_firmware_dir() {
+                file.origin = source.to_string();
+            }
+            files.push(file);
+        }
+    }
+    files
+}
+
+/// All firmware files in one directory: .srec, .hex and .bin files whose
+/// version is known from the filename, a `<file>.json` sidecar or the S0 header.
+/// See `scan_source_dir` for the files that are left out.
+fn read_source_dir(path: &Path) -> Vec<FirmwareFile> {
+    scan_source_dir(path).0
+}
+
+/// The firmware files in one directory, and the files with a firmware extension that
+/// were left out with the reason: empty files, names without a valid version, an S0
+/// header that contradicts the name (so an update never picks a mislabelled file) and
+/// a second file with a version already seen in the directory.
+fn scan_source_dir(path: &Path) -> (Vec<FirmwareFile>, Vec<(PathBuf, String)>) {
+    let dir = match fs::read_dir(path) {
         Ok(d) => d,
-        Err(_) => {
+        Err(_) if path == Path::new(FIRMWARE_DIR) => {
             eprintln!("Could not find the firmware folder");
-            return Vec::new();
+            return (Vec::new(), Vec::new());
+        }
+        Err(e) => {
+            eprintln!("Could not read firmware source {}: {e}", path.display());
+            return (Vec::new(), Vec::new());
         }
     };
-    dir.filter_map(|f| f.ok())
-        .filter_map(|f| f.file_name().to_str().map(str::to_string))
-        .filter(|n| n.ends_with(".srec"))
-        .filter_map(FirmwareVersion::from_filename)
-        .collect()
+    let mut paths: Vec<PathBuf> = dir
+        .filter_map(|f| f.ok())
+        .map(|f| f.path())
+        .filter(|p| p.is_file() && FirmwareFormat::from_path(p).is_some())
+        .collect();
+    paths.sort();
+
+    let mut files: Vec<FirmwareFile> = Vec::new();
+    let mut ignored = Vec::new();
+    for path in paths {
+        if fs::metadata(&path).is_ok_and(|m| m.len() == 0) {
+            ignored.push((path, "empty file".to_string()));
+            continue;
+        }
+        let file = match FirmwareFile::from_path(&path, None, None) {
+            Ok(file) => file,
+            Err(err) => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let reason = err.strip_prefix(&format!("{name}: ")).unwrap_or(&err).to_string();
+                ignored.push((path, reason));
+                continue;
+            }
+        };
+        if let Err(err) = check_header_version(&file, &read_file_start(&file.path)) {
+            ignored.push((path, err));
+            continue;
+        }
+        if let Some(other) = files.iter().find(|f| f.version == file.version) {
+            ignored.push((path, format!("same version as {}", other.file_name())));
+            continue;
+        }
+        files.push(file);
+    }
+    (files, ignored)
+}
+
+/// Report lines for the files every local firmware source leaves out, empty when there are none.
+fn ignored_firmware_report() -> Vec<String> {
+    let mut out = Vec::new();
+    for source in firmware_sources() {
+        let FirmwareSource::LocalDir { path } = source else {
+            continue;
+        };
+        for (file, reason) in scan_source_dir(path).1 {
+            out.push(format!("  {}: {reason}", file.display()));
+        }
+    }
+    if !out.is_empty() {
+        out.insert(0, "Ignored firmware files:".into());
+    }
+    out
+}
+
+/// The first KiB of a file as text, enough for the S0 header line.
+fn read_file_start(path: &Path) -> String {
+    let mut start = Vec::with_capacity(1024);
+    if let Ok(file) = File::open(path) {
+        if is_compressed(path) {
+            _ = GzDecoder::new(file).take(1024).read_to_end(&mut start);
+        } else {
+            _ = file.take(1024).read_to_end(&mut start);
+        }
+    }
+    String::from_utf8_lossy(&start).into_owned()
+}
+
+/// Whether a firmware file is stored gzip compressed, like 20-10-1-5-0-0-9.srec.gz.
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "gz")
+}
+
+/// `<file>.gz`, the name `check`, `import` and `firmware compress` store a firmware file under
+fn compressed_path(path: &Path) -> PathBuf {
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".gz");
+    PathBuf::from(compressed)
+}
+
+/// A firmware filename without the `.gz` of compressed storage, as it is named in manifests.
+fn uncompressed_name(name: &str) -> &str {
+    name.strip_suffix(".gz").unwrap_or(name)
+}
+
+/// Read a firmware file, decompressing it in memory when it is stored compressed.
+/// Checksums, signatures and parsing always work on the uncompressed firmware.
+fn read_firmware(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    if !is_compressed(path) {
+        return Ok(data);
+    }
+    let mut firmware = Vec::new();
+    GzDecoder::new(data.as_slice()).read_to_end(&mut firmware)?;
+    Ok(firmware)
+}
+
+/// gzip firmware for storage, S-record and Intel HEX text shrinks to well under half.
+fn compress_firmware(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// A command line option, `alias` is an alternative spelling of `name`.
+struct CliOption {
+    name: &'static str,
+    alias: Option<&'static str>,
+    takes_value: bool,
+}
+
+/// Every option the command line accepts, see `USAGE` for what they do.
+const OPTIONS: &[CliOption] = &[
+    CliOption { name: "--all", alias: None, takes_value: false },
+    CliOption { name: "--allow-downgrade", alias: None, takes_value: false },
+    CliOption { name: "--allow-unsigned", alias: None, takes_value: false },
+    CliOption { name: "--article", alias: None, takes_value: true },
+    CliOption { name: "--base-address", alias: None, takes_value: true },
+    CliOption { name: "--cloud-url", alias: None, takes_value: true },
+    CliOption { name: "--force", alias: None, takes_value: false },
+    CliOption { name: "--keep", alias: None, takes_value: true },
+    CliOption { name: "--scan", alias: None, takes_value: false },
+    CliOption { name: "--type", alias: None, takes_value: true },
+    CliOption { name: "--verbose", alias: Some("-v"), takes_value: false },
+    CliOption { name: "--version", alias: None, takes_value: true },
+];
+
+/// The command line split into positional arguments and options. Options can go anywhere
+/// on the line, a value follows as the next argument or after `=` as in `--keep=3`.
+#[derive(Default)]
+struct CommandLine {
+    positional: Vec<String>,
+    /// The given options by name, flags have an empty value.
+    options: HashMap<&'static str, String>,
+}
+
+impl CommandLine {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut line = CommandLine::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                line.positional.push(arg);
+                continue;
+            }
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            let option = OPTIONS
+                .iter()
+                .find(|o| o.name == name || o.alias == Some(name))
+                .ok_or_else(|| format!("Unknown option {name}"))?;
+            let value = match (option.takes_value, inline_value) {
+                (true, Some(value)) => value,
+                (true, None) => args.next().ok_or_else(|| format!("{} needs a value", option.name))?,
+                (false, Some(_)) => return Err(format!("{} does not take a value", option.name)),
+                (false, None) => String::new(),
+            };
+            line.options.insert(option.name, value);
+        }
+        Ok(line)
+    }
+
+    /// Whether the option `name` was given.
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    /// Value of the option `name` when it was given.
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
 }
 
 /// Sub-menu flow for the Update action. Returns the (possibly-unchanged)
@@ -2369,7 +4930,7 @@ fn read_firmware_dir() -> Vec<FirmwareVersion> {
 /// An empty `lines` vector signals that the user backed out of the sub-menus.
 async fn run_update_flow(
     modules: Vec<Module>,
-    available_firmwares: &[FirmwareVersion],
+    available_firmwares: &[FirmwareFile],
     multi_progress: &MultiProgress,
     style: &ProgressStyle,
     controller: ControllerTypes,
@@ -2466,7 +5027,7 @@ async fn run_update_flow(
 /// `run_update_flow`.
 async fn run_overwrite_flow(
     modules: Vec<Module>,
-    available_firmwares: &[FirmwareVersion],
+    available_firmwares: &[FirmwareFile],
     multi_progress: MultiProgress,
     style: ProgressStyle,
     controller: ControllerTypes,
@@ -2476,7 +5037,7 @@ async fn run_overwrite_flow(
     let mut remaining = modules;
 
     // Pick the module
-    let mut module = if let Some(arg) = slot_arg {
+    let module = if let Some(arg) = slot_arg {
         match arg.parse::<u8>() {
             Ok(slot) => match remaining.iter().position(|m| m.slot == slot) {
                 Some(idx) => remaining.remove(idx),
@@ -2504,26 +5065,89 @@ async fn run_overwrite_flow(
         }
     };
 
-    // Pick the firmware
-    let new_firmware = if let Some(arg) = firmware_arg {
-        match FirmwareVersion::from_filename(arg.clone()) {
-            Some(fw) if available_firmwares.contains(&fw) => fw,
-            Some(_) => {
-                remaining.push(module);
-                return (
-                    remaining,
-                    vec![format!("{}{} does not exist", FIRMWARE_DIR, arg)],
-                );
-            }
-            None => {
+    // Pick the firmware. A file, in the firmware directory or anywhere else when given
+    // as a path, is taken as is, with --version/--base-address filling in what its name,
+    // sidecar or header doesn't say. A selector picks from the firmware for the module's
+    // hardware. Otherwise the argument is matched against the versions in the firmware directory.
+    let reflash_selected = firmware_arg.as_deref() == Some("installed");
+    let library_file = |arg: &str| !arg.contains('/') && Path::new(FIRMWARE_DIR).join(arg).is_file();
+    let new_firmware = if let Some(selected) = firmware_arg
+        .as_deref()
+        .filter(|arg| !library_file(arg))
+        .and_then(|arg| resolve_selector(arg, &module, available_firmwares))
+    {
+        match selected {
+            Ok(file) => file,
+            Err(line) => {
                 remaining.push(module);
-                return (remaining, vec![format!("Invalid firmware entered: {arg}")]);
+                return (remaining, vec![line]);
+            }
+        }
+    } else if let Some(arg) = firmware_arg {
+        let version = match command_line().value("--version") {
+            Some(v) => match FirmwareVersion::from_filename(v.to_string()) {
+                Some(fw) => Some(fw),
+                None => {
+                    remaining.push(module);
+                    return (remaining, vec![format!("Invalid firmware version entered: {v}")]);
+                }
+            },
+            None => None,
+        };
+        let base_address = match command_line().value("--base-address") {
+            Some(b) => match parse_address(b) {
+                Some(addr) => Some(addr),
+                None => {
+                    remaining.push(module);
+                    return (remaining, vec![format!("Invalid base address entered: {b}")]);
+                }
+            },
+            None => None,
+        };
+        let path = if library_file(&arg) {
+            Some(Path::new(FIRMWARE_DIR).join(&arg))
+        } else if Path::new(&arg).is_file() {
+            Some(PathBuf::from(&arg))
+        } else {
+            None
+        };
+        if let Some(path) = path {
+            match FirmwareFile::from_path(&path, version, base_address) {
+                Ok(file) => file,
+                Err(err) => {
+                    remaining.push(module);
+                    return (remaining, vec![err]);
+                }
+            }
+        } else if arg.contains('/') {
+            remaining.push(module);
+            return (remaining, vec![format!("{arg} does not exist")]);
+        } else {
+            match FirmwareVersion::from_filename(arg.clone()) {
+                Some(fw) => match available_firmwares.iter().find(|f| f.version == fw) {
+                    Some(file) => file.clone(),
+                    None => {
+                        remaining.push(module);
+                        return (
+                            remaining,
+                            vec![format!("{}{} does not exist", FIRMWARE_DIR, arg)],
+                        );
+                    }
+                },
+                None => {
+                    remaining.push(module);
+                    return (remaining, vec![format!("Invalid firmware entered: {arg}")]);
+                }
             }
         }
     } else {
-        let valid: Vec<&FirmwareVersion> = available_firmwares
+        let valid: Vec<PickerEntry> = available_firmwares
             .iter()
-            .filter(|f| f.get_hardware() == module.firmware.get_hardware())
+            .filter(|f| f.fit(&module.firmware).fits())
+            .map(|file| PickerEntry {
+                file,
+                note: file.cross_revision_note(&module.firmware),
+            })
             .collect();
         if valid.is_empty() {
             remaining.push(module);
@@ -2531,7 +5155,7 @@ async fn run_overwrite_flow(
         }
         redraw_chrome("Select firmware to upload:");
         match run_select("Which firmware to upload?", valid, MenuMode::Sub) {
-            SelectResult::Selected(fw) => *fw,
+            SelectResult::Selected(entry) => entry.file.clone(),
             SelectResult::Back | SelectResult::Quit => {
                 remaining.push(module);
                 return (remaining, Vec::new());
@@ -2539,22 +5163,185 @@ async fn run_overwrite_flow(
         }
     };
 
-    // Run the upload
+    // The picker only offers fitting firmware, a file given on the command line can be anything.
+    if !new_firmware.fit(&module.firmware).fits() {
+        let line = format!(
+            "{} is built for hardware {} which is not compatible with the module in slot {} ({}), not flashing",
+            new_firmware,
+            hardware_string(&new_firmware.version),
+            module.slot,
+            hardware_string(&module.firmware)
+        );
+        remaining.push(module);
+        return (remaining, vec![line]);
+    }
+
+    // Flashing an older or the same version needs to be asked for explicitly: --allow-downgrade
+    // on the command line, a confirmation naming both versions in the TUI.
+    let current = module.firmware;
+    let kind = downgrade_kind(&current, &new_firmware.version, reflash_selected);
+    if let Some(kind) = kind {
+        let allowed = if STARTED_FROM_CLI.load(Ordering::Relaxed) {
+            command_line().flag("--allow-downgrade")
+        } else {
+            run_confirm(
+                &format!(
+                    "Slot {} runs {}, {} it to {}?",
+                    module.slot,
+                    firmware_version_string(&current),
+                    kind,
+                    firmware_version_string(&new_firmware.version)
+                ),
+                false,
+            )
+        };
+        if !allowed {
+            let line = format!(
+                "Not flashing slot {}: {} from {} to {} {}",
+                module.slot,
+                kind,
+                current.as_string(),
+                new_firmware.version.as_string(),
+                if STARTED_FROM_CLI.load(Ordering::Relaxed) {
+                    "needs --allow-downgrade"
+                } else {
+                    "declined"
+                }
+            );
+            remaining.push(module);
+            return (remaining, vec![line]);
+        }
+    }
+
+    let slot = module.slot;
+    let (flashed, mut lines) = flash_file(module, &new_firmware, multi_progress, style, controller).await;
+    if let (true, Some(kind)) = (flashed, kind) {
+        lines.push(format!(
+            "Slot {slot}: {kind} from {} to {} was explicitly allowed",
+            firmware_version_string(&current),
+            firmware_version_string(&new_firmware.version)
+        ));
+    }
+    (Vec::new(), lines)
+}
+
+/// A file in the overwrite picker, marked when it is built for another, compatible
+/// hardware revision than the module's.
+struct PickerEntry<'a> {
+    file: &'a FirmwareFile,
+    note: String,
+}
+
+impl Display for PickerEntry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.file, self.note)
+    }
+}
+
+/// "downgrade" or "reflash" when flashing `new` over `current` goes back or stays on the
+/// same software version, which has to be explicitly allowed. A reflash asked for with
+/// the `installed` selector is allowed already.
+fn downgrade_kind(
+    current: &FirmwareVersion,
+    new: &FirmwareVersion,
+    reflash_selected: bool,
+) -> Option<&'static str> {
+    let uninitialized = current.get_software() == [255u8, 255, 255];
+    match new.get_software().cmp(current.get_software()) {
+        std::cmp::Ordering::Less if !uninitialized => Some("downgrade"),
+        std::cmp::Ordering::Equal if !reflash_selected => Some("reflash"),
+        _ => None,
+    }
+}
+
+/// Resolve an overwrite selector against the firmware that fits the module's hardware:
+/// `latest`, `previous` (the newest version older than the installed one), `installed`
+/// (reflash) or a software version like `1.2.3`. None when `arg` is not a selector.
+fn resolve_selector(
+    arg: &str,
+    module: &Module,
+    available_firmwares: &[FirmwareFile],
+) -> Option<Result<FirmwareFile, String>> {
+    // Newest first, a file built for the module's own revision before a compatible one
+    let mut candidates: Vec<&FirmwareFile> = available_firmwares
+        .iter()
+        .filter(|f| f.fit(&module.firmware).fits())
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.version
+            .get_software()
+            .cmp(a.version.get_software())
+            .then_with(|| {
+                let exact = |f: &FirmwareFile| f.fit(&module.firmware) == HardwareFit::Exact;
+                exact(b).cmp(&exact(a))
+            })
+    });
+    let installed = module.firmware.get_software();
+    let found = match arg {
+        "latest" => candidates.first().copied(),
+        // An uninitialized module (255.255.255) has no firmware to go back from
+        "previous" if installed == [255u8, 255, 255] => None,
+        "previous" => candidates
+            .iter()
+            .find(|f| f.version.get_software() < installed)
+            .copied(),
+        "installed" => candidates
+            .iter()
+            .find(|f| f.version.get_software() == installed)
+            .copied(),
+        _ => {
+            let numbers: Vec<u8> = arg.split('.').map_while(|n| n.parse().ok()).collect();
+            if numbers.len() != 3 || arg.split('.').count() != 3 {
+                return None;
+            }
+            candidates
+                .iter()
+                .find(|f| f.version.get_software() == numbers)
+                .copied()
+        }
+    };
+    Some(found.cloned().ok_or_else(|| {
+        let versions: Vec<String> = candidates
+            .iter()
+            .map(|f| firmware_version_string(&f.version))
+            .collect();
+        format!(
+            "No firmware matches {arg} for slot {} ({} installed), available for {}: {}",
+            module.slot,
+            firmware_version_string(&module.firmware),
+            hardware_string(&module.firmware),
+            if versions.is_empty() {
+                "none".to_string()
+            } else {
+                versions.join(", ")
+            }
+        )
+    }))
+}
+
+/// Flash `file` onto `module` through `overwrite_module` and record the result in
+/// modules.json, which also remembers the replaced firmware for `rollback`.
+/// Returns whether the new firmware is on the module, with the result lines.
+async fn flash_file(
+    mut module: Module,
+    file: &FirmwareFile,
+    multi_progress: MultiProgress,
+    style: ProgressStyle,
+    controller: ControllerTypes,
+) -> (bool, Vec<String>) {
     let original = module.firmware.as_string();
-    match module
-        .overwrite_module(&new_firmware, multi_progress, style)
-        .await
-    {
+    match module.overwrite_module(file, multi_progress, style).await {
         Ok(()) => {
             let line = format!(
-                "Successfully updated slot {} from {} to {}",
+                "Successfully updated slot {} from {} to {}{}",
                 module.slot,
                 original,
-                new_firmware.as_string()
+                file.version.as_string(),
+                file.cross_revision_note(&module.firmware)
             );
-            module.firmware = new_firmware;
+            module.firmware = file.version.flashed_onto(&module.firmware);
             save_modules(vec![Some(module)], &controller);
-            (Vec::new(), vec![line])
+            (true, vec![line])
         }
         Err(UploadError::FirmwareCorrupted(slot)) => {
             let mut lines = vec![format!(
@@ -2562,28 +5349,194 @@ async fn run_overwrite_flow(
             )];
             module.wipe_module_error().await;
             lines.push(format!("Update failed, firmware is corrupted on slot {slot}"));
-            (Vec::new(), lines)
+            (false, lines)
         }
-        Err(UploadError::FirmwareUntouched(slot)) => {
-            (Vec::new(), vec![format!("Update failed on slot {slot}")])
+        Err(UploadError::FirmwareUntouched(slot)) => (false, vec![format!("Update failed on slot {slot}")]),
+    }
+}
+
+/// The file to roll `module` back to: the previous firmware recorded for its slot,
+/// which must still be in the library, built for the module's hardware revision or a
+/// compatible one.
+fn rollback_file(
+    module: &Module,
+    previous: &[(u8, FirmwareVersion)],
+    available_firmwares: &[FirmwareFile],
+) -> Result<FirmwareFile, String> {
+    let slot = module.slot;
+    let Some((_, version)) = previous.iter().find(|(s, _)| *s == slot) else {
+        return Err(format!("No previous firmware recorded for slot {slot}"));
+    };
+    if version.get_hardware() != module.firmware.get_hardware() {
+        return Err(format!(
+            "Previous firmware {} of slot {slot} does not match the module now in it",
+            version.as_string()
+        ));
+    }
+    available_firmwares
+        .iter()
+        .filter(|f| f.provides(version))
+        .max_by_key(|f| f.fit(version) == HardwareFit::Exact)
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "Previous firmware {} of slot {slot} is no longer in {FIRMWARE_DIR}, not rolling back",
+                version.as_string()
+            )
+        })
+}
+
+/// Roll one module back, see `rollback_file`.
+async fn rollback_one_module(
+    module: Module,
+    previous: &[(u8, FirmwareVersion)],
+    available_firmwares: &[FirmwareFile],
+    multi_progress: &MultiProgress,
+    style: &ProgressStyle,
+    controller: ControllerTypes,
+) -> Vec<String> {
+    match rollback_file(&module, previous, available_firmwares) {
+        Ok(file) => flash_file(module, &file, multi_progress.clone(), style.clone(), controller).await.1,
+        Err(line) => vec![line],
+    }
+}
+
+/// Sub-menu flow for the Rollback action: re-flash the firmware each slot ran
+/// before its last flash. Same return contract as `run_update_flow`.
+async fn run_rollback_flow(
+    modules: Vec<Module>,
+    available_firmwares: &[FirmwareFile],
+    multi_progress: &MultiProgress,
+    style: &ProgressStyle,
+    controller: ControllerTypes,
+    cli_arg: Option<String>,
+) -> (Vec<Module>, Vec<String>) {
+    let previous = previous_firmware();
+    let all = match cli_arg.as_deref() {
+        Some("all") => true,
+        Some(other) => {
+            let Ok(slot) = other.parse::<u8>() else {
+                return (modules, vec![format!("Invalid slot: {other}")]);
+            };
+            let mut remaining = modules;
+            let Some(idx) = remaining.iter().position(|m| m.slot == slot) else {
+                return (remaining, vec![format!("Couldn't find a module in slot {slot}")]);
+            };
+            let module = remaining.remove(idx);
+            let lines = rollback_one_module(
+                module,
+                &previous,
+                available_firmwares,
+                multi_progress,
+                style,
+                controller,
+            )
+            .await;
+            return (Vec::new(), lines);
+        }
+        None => {
+            redraw_chrome("Select your rollback method:");
+            match run_select("Roll back one module or all?", vec!["all", "one"], MenuMode::Sub) {
+                SelectResult::Selected("all") => true,
+                SelectResult::Selected("one") => false,
+                SelectResult::Selected(_) | SelectResult::Back | SelectResult::Quit => {
+                    return (modules, Vec::new())
+                }
+            }
+        }
+    };
+
+    let (candidates, others): (Vec<Module>, Vec<Module>) = modules
+        .into_iter()
+        .partition(|m| previous.iter().any(|(slot, _)| *slot == m.slot));
+    if candidates.is_empty() {
+        return (others, vec!["No previous firmware recorded for any module.".into()]);
+    }
+    let selected = if all {
+        candidates
+    } else {
+        redraw_chrome("Select module to roll back:");
+        match run_select("Select a module to roll back", candidates, MenuMode::Sub) {
+            SelectResult::Selected(module) => vec![module],
+            SelectResult::Back | SelectResult::Quit => return (Vec::new(), Vec::new()),
         }
+    };
+    let mut lines = Vec::new();
+    for module in selected {
+        lines.extend(
+            rollback_one_module(
+                module,
+                &previous,
+                available_firmwares,
+                multi_progress,
+                style,
+                controller,
+            )
+            .await,
+        );
     }
+    (Vec::new(), lines)
 }
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 3)]
 async fn main() {
     redraw_chrome("");
 
-    let cli_arg1 = env::args().nth(1);
-    let cli_arg2 = env::args().nth(2);
-    let cli_arg3 = env::args().nth(3);
+    match CommandLine::parse(env::args().skip(1)) {
+        Ok(line) => _ = COMMAND_LINE.set(line),
+        Err(e) => err_n_die(&format!("{e}\n{USAGE}")),
+    }
+    let mut positional = command_line().positional.iter().cloned();
+    let cli_arg1 = positional.next();
+    let cli_arg2 = positional.next();
+    let cli_arg3 = positional.next();
+
+    let allow_unsigned = command_line().flag("--allow-unsigned")
+        || env::var("GO_MODULES_ALLOW_UNSIGNED").is_ok_and(|v| v == "1");
+    if allow_unsigned {
+        eprintln!("Warning: signature verification is disabled, unsigned firmware will be accepted");
+    }
+    match pins::PinPolicy::load(PINS_PATH) {
+        Ok(policy) => _ = PIN_POLICY.set(policy),
+        Err(e) => err_n_die(&format!("Invalid version pin policy {e}")),
+    }
+    match sources::load(CONFIG_PATH) {
+        Ok(config) => {
+            ALLOW_UNSIGNED.store(allow_unsigned || !config.require_signatures, Ordering::Relaxed);
+            _ = FIRMWARE_SOURCES.set(config.sources);
+            // --cloud-url, then GO_MODULES_CLOUD_URL, replace the configured cloud endpoints
+            let cloud = match command_line()
+                .value("--cloud-url")
+                .map(str::to_string)
+                .or_else(|| env::var("GO_MODULES_CLOUD_URL").ok()) {
+                Some(urls) => sources::CloudEndpoints::parse_list(&urls)
+                    .unwrap_or_else(|e| err_n_die(&format!("Invalid cloud URL: {e}"))),
+                None => config.cloud,
+            };
+            _ = CLOUD_ENDPOINTS.set(cloud);
+        }
+        Err(e) => err_n_die(&format!("Invalid configuration {e}")),
+    }
+    match compat::CompatibilityRules::load(COMPATIBILITY_PATH) {
+        Ok(rules) => _ = COMPATIBILITY.set(rules),
+        Err(e) => err_n_die(&format!("Invalid hardware compatibility rules {e}")),
+    }
 
+    let check_all = command_line().flag("--all");
     // Handle the check command early — before hardware detection, service
     // management, and module scanning. Allows `check` to run on any system
     // with network access, without requiring SPI hardware.
     if cli_arg1.as_deref() == Some("check") {
-        let verbose = env::args().any(|a| a == "--verbose" || a == "-v");
-        match check_firmware(verbose).await {
+        let verbose = command_line().flag("--verbose");
+        let fitted: Vec<FirmwareVersion> = if check_all {
+            Vec::new()
+        } else if command_line().flag("--scan") {
+            scanned_firmware().await
+        } else {
+            // without scanning, the modules found by the last scan
+            installed_firmware().into_iter().map(|(_, fw)| fw).collect()
+        };
+        match check_firmware(verbose, check_scope(&fitted, check_all)).await {
             Ok(lines) => {
                 for line in &lines {
                     println!("{line}");
@@ -2597,6 +5550,85 @@ async fn main() {
         }
     }
 
+    // Like check, releases and download only need the network.
+    if matches!(cli_arg1.as_deref(), Some("releases" | "download")) {
+        let result = match (cli_arg1.as_deref(), cli_arg2.as_deref(), cli_arg3.as_deref()) {
+            (Some("releases"), Some(target), _) => list_releases(target).await,
+            (Some("download"), Some(hardware), Some(version)) => download_version(hardware, version).await,
+            (Some("releases"), ..) => Err(format!("releases needs a slot, hardware version or module type\n{}", USAGE)),
+            _ => Err(format!("download needs a hardware version and a software version\n{}", USAGE)),
+        };
+        match result {
+            Ok(lines) => {
+                for line in &lines {
+                    println!("{line}");
+                }
+                exit(0);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                exit(1);
+            }
+        }
+    }
+
+    // Like check, verify only looks at files and runs without hardware.
+    if cli_arg1.as_deref() == Some("verify") {
+        let Some(target) = cli_arg2.as_deref() else {
+            err_n_die(&format!("verify needs a target\n{}", USAGE));
+        };
+        match verify_firmware(target) {
+            Ok((lines, all_ok)) => {
+                for line in &lines {
+                    println!("{line}");
+                }
+                exit(if all_ok { 0 } else { 1 });
+            }
+            Err(e) => {
+                eprintln!("Error verifying firmware: {e}");
+                exit(1);
+            }
+        }
+    }
+
+    // Library management works on files only and runs without hardware.
+    if matches!(cli_arg1.as_deref(), Some("firmware" | "import" | "export")) {
+        let result = match (cli_arg1.as_deref(), cli_arg2.as_deref()) {
+            (Some("import"), Some(source)) => import_bundle(source),
+            (Some("export"), Some(target)) => export_bundle(target),
+            (Some("import" | "export"), None) => Err(format!("Missing bundle path\n{}", USAGE)),
+            (_, sub) => match sub {
+                Some("list") => Ok(firmware_list()),
+                Some("prune") => match command_line().value("--keep").map(|k| k.parse::<usize>()) {
+                    Some(Ok(keep)) if keep > 0 => Ok(firmware_prune(keep)),
+                    _ => Err("prune needs --keep <n> with n at least 1".to_string()),
+                },
+                Some("compress") => Ok(firmware_compress()),
+                Some("info") => match cli_arg3.as_deref() {
+                    Some(name) => firmware_info(name),
+                    None => Err("info needs a firmware file".to_string()),
+                },
+                Some("remove") => match cli_arg3.as_deref() {
+                    Some(name) => firmware_remove(name, command_line().flag("--force")),
+                    None => Err("remove needs a firmware file".to_string()),
+                },
+                _ => Err(format!("Invalid firmware command\n{}", USAGE)),
+            },
+        };
+        match result {
+            Ok(lines) => {
+                for line in &lines {
+                    println!("{line}");
+                }
+                exit(0);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                exit(1);
+            }
+        }
+    }
+
     // Detect controller
     let controller = detect_controller();
 
@@ -2634,26 +5666,6 @@ async fn main() {
     // Scan modules in parallel with the rest of init
     let modules_fut = task::spawn(get_modules_and_save(controller));
 
-    // Resolve firmware directory; offer download if missing
-    let mut available_firmwares: Vec<FirmwareVersion> = if fs::metadata(FIRMWARE_DIR).is_err() {
-        println!("No firmware found on this controller.");
-        if run_confirm("Do you want to download the latest firmware?", true) {
-            match check_firmware(false).await {
-                Ok(lines) => {
-                    for line in &lines {
-                        println!("{line}");
-                    }
-                }
-                Err(e) => eprintln!("Error downloading firmware: {e}"),
-            }
-            read_firmware_dir()
-        } else {
-            Vec::new()
-        }
-    } else {
-        read_firmware_dir()
-    };
-
     // Progress bar style (multi_progress is created fresh per action to avoid stale bars)
     let style = ProgressStyle::with_template("{bar:40.cyan/blue} {pos:>7}/{len:7} ({eta}) {msg}")
         .unwrap()
@@ -2672,12 +5684,34 @@ async fn main() {
         }
     };
 
+    // Resolve firmware directory; offer download if missing, for the modules just found
+    let mut available_firmwares: Vec<FirmwareFile> = if fs::metadata(FIRMWARE_DIR).is_err() {
+        println!("No firmware found on this controller.");
+        if run_confirm("Do you want to download the latest firmware?", true) {
+            let fitted: Vec<FirmwareVersion> = modules.iter().map(|m| m.firmware).collect();
+            match check_firmware(false, check_scope(&fitted, check_all)).await {
+                Ok(lines) => {
+                    for line in &lines {
+                        println!("{line}");
+                    }
+                }
+                Err(e) => eprintln!("Error downloading firmware: {e}"),
+            }
+            read_firmware_dir()
+        } else {
+            Vec::new()
+        }
+    } else {
+        read_firmware_dir()
+    };
+
     // Initial action from CLI args (if any). Subsequent loop iterations come
     // from the main menu. Sub-actions never close the app.
     let mut next_action: Option<CommandArg> = match cli_arg1.as_deref() {
         Some("scan") => Some(CommandArg::Scan),
         Some("update") => Some(CommandArg::Update),
         Some("overwrite") => Some(CommandArg::Overwrite),
+        Some("rollback") => Some(CommandArg::Rollback),
         None => None,
         Some(other) => {
             eprintln!("Invalid command entered {}\n{}", other, USAGE);
@@ -2706,6 +5740,8 @@ async fn main() {
                         CommandArg::Update,
                         CommandArg::Overwrite,
                         CommandArg::Check,
+                        CommandArg::Verify,
+                        CommandArg::Rollback,
                     ],
                     MenuMode::Main,
                 ) {
@@ -2719,14 +5755,21 @@ async fn main() {
         match action {
             CommandArg::Scan => {
                 redraw_chrome("Result of scanned modules:");
-                if modules.is_empty() {
-                    show_view(&["No modules found".into()]);
+                let mut lines = if modules.is_empty() {
+                    vec!["No modules found".into()]
                 } else {
-                    show_view(&format_module_lines(&modules, &available_firmwares));
+                    format_module_lines(&modules, &available_firmwares)
+                };
+                let ignored = ignored_firmware_report();
+                if !ignored.is_empty() {
+                    lines.push(String::new());
+                    lines.extend(ignored);
                 }
+                show_view(&lines);
             }
             CommandArg::Check => {
-                let (subtitle, lines) = match check_firmware(false).await {
+                let fitted: Vec<FirmwareVersion> = modules.iter().map(|m| m.firmware).collect();
+                let (subtitle, lines) = match check_firmware(false, check_scope(&fitted, check_all)).await {
                     Ok(mut l) => {
                         let date = l.first()
                             .and_then(|s| s.strip_prefix("Cloud manifest last updated: "))
@@ -2749,6 +5792,13 @@ async fn main() {
                 available_firmwares = read_firmware_dir();
                 show_view(&lines);
             }
+            CommandArg::Verify => {
+                redraw_chrome("Firmware verification:");
+                match verify_firmware("all") {
+                    Ok((lines, _)) => show_view(&lines),
+                    Err(e) => show_view(&[format!("Error verifying firmware: {e}")]),
+                }
+            }
             CommandArg::Update => {
                 let owned = std::mem::take(&mut modules);
                 let multi_progress = MultiProgress::new();
@@ -2772,6 +5822,29 @@ async fn main() {
                     show_view(&lines);
                 }
             }
+            CommandArg::Overwrite
+                if command_line().flag("--type") || command_line().flag("--article") =>
+            {
+                let owned = std::mem::take(&mut modules);
+                let (returned, lines) = run_batch_overwrite(
+                    owned,
+                    &available_firmwares,
+                    &MultiProgress::new(),
+                    &style,
+                    controller,
+                    cli_arg2.clone(),
+                )
+                .await;
+                modules = if returned.is_empty() {
+                    get_modules(&controller).await
+                } else {
+                    returned
+                };
+                if !lines.is_empty() {
+                    redraw_chrome("Overwrite result:");
+                    show_view(&lines);
+                }
+            }
             CommandArg::Overwrite => {
                 let owned = std::mem::take(&mut modules);
                 let (returned, lines) = run_overwrite_flow(
@@ -2794,6 +5867,27 @@ async fn main() {
                     show_view(&lines);
                 }
             }
+            CommandArg::Rollback => {
+                let owned = std::mem::take(&mut modules);
+                let (returned, lines) = run_rollback_flow(
+                    owned,
+                    &available_firmwares,
+                    &MultiProgress::new(),
+                    &style,
+                    controller,
+                    cli_arg2.clone(),
+                )
+                .await;
+                modules = if returned.is_empty() {
+                    get_modules(&controller).await
+                } else {
+                    returned
+                };
+                if !lines.is_empty() {
+                    redraw_chrome("Rollback result:");
+                    show_view(&lines);
+                }
+            }
         }
 
         if started_from_cli {
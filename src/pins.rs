@@ -0,0 +1,292 @@
+//! Firmware version pinning.
+//!
+//! A certified machine configuration must keep the firmware it was certified
+//! with, even when newer files land in the firmware directory. The policy file
+//! pins a slot, an article number or a module type to a version constraint:
+//!
+//! ```json
+//! {
+//!     "slots": { "3": "1.4.2" },
+//!     "articles": { "20100105": ">=1.4, <2" },
+//!     "module_types": { "output-6ch": "=0.1" }
+//! }
+//! ```
+//!
+//! The most specific pin wins: slot, then article number, then module type.
+
+use std::{collections::HashMap, fmt, fs, io};
+
+use serde::Deserialize;
+
+use crate::{article_number_from_firmware, FirmwareVersion, ModuleType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// One comparison like `>=1.4`. `parts` is how many version numbers were
+/// written: an exact match on `1.4` accepts every 1.4.x, comparisons fill the
+/// missing numbers with zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    version: [u8; 3],
+    parts: usize,
+}
+
+impl Comparator {
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (op, rest) = [
+            (">=", Op::Ge),
+            ("<=", Op::Le),
+            ("==", Op::Eq),
+            (">", Op::Gt),
+            ("<", Op::Lt),
+            ("=", Op::Eq),
+        ]
+        .iter()
+        .find_map(|(prefix, op)| text.strip_prefix(prefix).map(|rest| (*op, rest)))
+        .unwrap_or((Op::Eq, text));
+        let numbers: Vec<&str> = rest.trim().split('.').collect();
+        if numbers.len() > 3 {
+            return Err(format!("invalid version in \"{text}\""));
+        }
+        let mut version = [0u8; 3];
+        for (part, number) in version.iter_mut().zip(&numbers) {
+            *part = number
+                .parse()
+                .map_err(|_| format!("invalid version in \"{text}\""))?;
+        }
+        Ok(Self {
+            op,
+            version,
+            parts: numbers.len(),
+        })
+    }
+
+    fn matches(&self, software: &[u8]) -> bool {
+        let version = &self.version[..];
+        match self.op {
+            Op::Eq => software.get(..self.parts) == version.get(..self.parts),
+            Op::Lt => software < version,
+            Op::Le => software <= version,
+            Op::Gt => software > version,
+            Op::Ge => software >= version,
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Eq => "=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        };
+        let version: Vec<String> = self.version[..self.parts]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        write!(f, "{op}{}", version.join("."))
+    }
+}
+
+/// A software version constraint: comma separated comparators that must all
+/// hold, e.g. `>=1.4, <2`. A bare version is an exact match.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Constraint(Vec<Comparator>);
+
+impl Constraint {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let comparators = text
+            .split(',')
+            .map(Comparator::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(comparators))
+    }
+
+    pub fn matches(&self, software: &[u8]) -> bool {
+        self.0.iter().all(|c| c.matches(software))
+    }
+}
+
+impl TryFrom<String> for Constraint {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        Self::parse(&text)
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|c| c.to_string()).collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// The pin that applies to a module, with where it came from for messages.
+pub struct Pin<'a> {
+    pub scope: String,
+    pub constraint: &'a Constraint,
+}
+
+impl fmt::Display for Pin<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} pinned to {}", self.scope, self.constraint)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinPolicy {
+    #[serde(default)]
+    slots: HashMap<u8, Constraint>,
+    #[serde(default)]
+    articles: HashMap<u32, Constraint>,
+    #[serde(default)]
+    module_types: HashMap<ModuleType, Constraint>,
+}
+
+impl PinPolicy {
+    /// Read the policy file. A missing file is an empty policy, a file that
+    /// doesn't parse is an error: silently ignoring it would let a certified
+    /// machine drift.
+    pub fn load(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| format!("{path}: {e}")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{path}: {e}")),
+        }
+    }
+
+    /// The pin for firmware `fw` in `slot`, or for any slot when `slot` is None.
+    pub fn pin_for(&self, slot: Option<u8>, fw: &FirmwareVersion) -> Option<Pin<'_>> {
+        if let Some(constraint) = slot.and_then(|s| self.slots.get(&s)) {
+            return Some(Pin {
+                scope: format!("slot {}", slot.unwrap_or_default()),
+                constraint,
+            });
+        }
+        let article = article_number_from_firmware(fw);
+        if let Some(constraint) = self.articles.get(&article) {
+            return Some(Pin {
+                scope: format!("article {article}"),
+                constraint,
+            });
+        }
+        let module_type = ModuleType::from_firmware(fw)?;
+        self.module_types.get(&module_type).map(|constraint| Pin {
+            scope: format!("{} modules", fw.type_name()),
+            constraint,
+        })
+    }
+
+    /// Whether `fw` may be installed in `slot` (or in any slot when None).
+    pub fn allows(&self, slot: Option<u8>, fw: &FirmwareVersion) -> bool {
+        self.pin_for(slot, fw)
+            .is_none_or(|pin| pin.constraint.matches(fw.get_software()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fw(version: &str) -> FirmwareVersion {
+        FirmwareVersion::from_filename(version.to_string()).unwrap()
+    }
+
+    fn matches(constraint: &str, software: [u8; 3]) -> bool {
+        Constraint::parse(constraint).unwrap().matches(&software)
+    }
+
+    #[test]
+    fn bare_version_is_exact_match_on_given_parts() {
+        assert!(matches("1.4.2", [1, 4, 2]));
+        assert!(!matches("1.4.2", [1, 4, 3]));
+        assert!(matches("1.4", [1, 4, 0]));
+        assert!(matches("=1.4", [1, 4, 9]));
+        assert!(!matches("1.4", [1, 5, 0]));
+        assert!(matches("1", [1, 9, 9]));
+        assert!(matches("==0.1", [0, 1, 3]));
+    }
+
+    #[test]
+    fn comparisons_fill_with_zeros() {
+        assert!(matches(">=1.4", [1, 4, 0]));
+        assert!(!matches(">=1.4", [1, 3, 9]));
+        assert!(matches(">1.4", [1, 4, 1]));
+        assert!(!matches(">1.4", [1, 4, 0]));
+        assert!(matches("<2", [1, 255, 255]));
+        assert!(!matches("<2", [2, 0, 0]));
+        assert!(matches("<=2", [2, 0, 0]));
+    }
+
+    #[test]
+    fn all_comparators_must_hold() {
+        assert!(matches(">=1.4, <2", [1, 7, 0]));
+        assert!(!matches(">=1.4, <2", [2, 0, 0]));
+        assert!(!matches(">=1.4, <2", [1, 3, 0]));
+        assert!(matches(" >= 1.4 ,< 2 ", [1, 4, 0]));
+    }
+
+    #[test]
+    fn rejects_invalid_constraints() {
+        for text in ["", "1.x", "1.2.3.4", ">=", "1.2,", "256", "~1.2"] {
+            assert!(Constraint::parse(text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn displays_constraint() {
+        let constraint = Constraint::parse(">=1.4, <2, 1.4.2").unwrap();
+        assert_eq!(constraint.to_string(), ">=1.4, <2, =1.4.2");
+    }
+
+    #[test]
+    fn most_specific_pin_wins() {
+        let policy: PinPolicy = serde_json::from_str(
+            r#"{
+                "slots": { "3": "1.4.2" },
+                "articles": { "20100105": ">=1.4, <2" },
+                "module_types": { "input-6ch": "=0.1" }
+            }"#,
+        )
+        .unwrap();
+        let input = fw("20-10-1-5-1-4-2");
+        assert_eq!(policy.pin_for(Some(3), &input).unwrap().to_string(), "slot 3 pinned to =1.4.2");
+        assert_eq!(
+            policy.pin_for(Some(1), &input).unwrap().to_string(),
+            "article 20100105 pinned to >=1.4, <2"
+        );
+        let other_revision = fw("20-10-1-4-0-1-0");
+        assert_eq!(
+            policy.pin_for(None, &other_revision).unwrap().scope,
+            "6 Channel Input modules"
+        );
+        assert!(policy.pin_for(None, &fw("20-20-2-6-1-0-0")).is_none());
+
+        assert!(policy.allows(Some(3), &input));
+        assert!(!policy.allows(Some(3), &fw("20-10-1-5-1-5-0")));
+        assert!(policy.allows(Some(1), &fw("20-10-1-5-1-5-0")));
+        assert!(!policy.allows(None, &fw("20-10-1-4-0-2-0")));
+        assert!(policy.allows(None, &fw("20-20-2-6-9-9-9")));
+    }
+
+    #[test]
+    fn rejects_invalid_policy() {
+        assert!(serde_json::from_str::<PinPolicy>(r#"{ "slots": { "1": "x" } }"#).is_err());
+        assert!(serde_json::from_str::<PinPolicy>(r#"{ "slot": {} }"#).is_err());
+        assert!(serde_json::from_str::<PinPolicy>(r#"{ "module_types": { "foo": "1" } }"#).is_err());
+    }
+}
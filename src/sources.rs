@@ -0,0 +1,206 @@
+//! Where firmware comes from.
+//!
+//! The sources are configured as an ordered list in the config file, earlier
+//! sources take precedence when two of them offer the same version:
+//!
+//! ```json
+//! {
+//!     "sources": [
+//!         { "type": "local-dir", "path": "/opt/customer-firmware" },
+//!         { "type": "http-mirror", "url": "http://firmware.plant.local" },
+//!         { "type": "cloud" }
+//!     ],
+//!     "cloud": {
+//!         "url": "https://firmware.gocontroll.com",
+//!         "mirrors": ["https://firmware-backup.example.com"]
+//!     },
+//!     "require_signatures": true
+//! }
+//! ```
+//!
+//! Local directories are read in place. Remote sources, mirrors and the
+//! GOcontroll cloud, share the cloud's manifest layout; `check` downloads from
+//! them into the firmware directory, which is always part of the catalogue.
+//!
+//! The cloud is one source reachable through several endpoints: its URL and
+//! fallback mirrors are tried in order, the first that answers serves the
+//! manifests. `GO_MODULES_CLOUD_URL` and `--cloud-url` replace the configured
+//! endpoints with a comma separated list, e.g. a local test server.
+//!
+//! `require_signatures` turns on refusing firmware and manifests without a
+//! valid signature. It is off by default while controllers get the trusted
+//! keys installed, unsigned content is then accepted with a warning.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{CLOUD_BASE_URL, FIRMWARE_DIR};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum FirmwareSource {
+    /// A directory of firmware files.
+    LocalDir { path: PathBuf },
+    /// A server with the same layout as the GOcontroll cloud, e.g. a plant mirror.
+    HttpMirror { url: String },
+    /// The GOcontroll cloud.
+    Cloud,
+}
+
+impl FirmwareSource {
+    /// Base URLs of a remote source in the order to try them, empty for a local directory.
+    /// The cloud is reached through `cloud`.
+    pub fn base_urls<'a>(&'a self, cloud: &'a CloudEndpoints) -> Vec<&'a str> {
+        match self {
+            Self::LocalDir { .. } => Vec::new(),
+            Self::HttpMirror { url } => vec![url.trim_end_matches('/')],
+            Self::Cloud => cloud.urls().collect(),
+        }
+    }
+
+    pub fn is_remote(&self) -> bool {
+        !matches!(self, Self::LocalDir { .. })
+    }
+
+    /// Whether this is the firmware directory that downloads and imports go to.
+    pub fn is_firmware_dir(&self) -> bool {
+        matches!(self, Self::LocalDir { path } if path == Path::new(FIRMWARE_DIR))
+    }
+}
+
+impl fmt::Display for FirmwareSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LocalDir { path } => write!(f, "{}", path.display()),
+            Self::HttpMirror { url } => write!(f, "mirror {url}"),
+            Self::Cloud => write!(f, "cloud"),
+        }
+    }
+}
+
+/// Where the GOcontroll cloud is reached: its URL, then fallback mirrors with the same content.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CloudEndpoints {
+    #[serde(default = "default_cloud_url")]
+    url: String,
+    #[serde(default)]
+    mirrors: Vec<String>,
+}
+
+fn default_cloud_url() -> String {
+    CLOUD_BASE_URL.to_string()
+}
+
+impl Default for CloudEndpoints {
+    fn default() -> Self {
+        Self {
+            url: default_cloud_url(),
+            mirrors: Vec::new(),
+        }
+    }
+}
+
+impl CloudEndpoints {
+    /// A comma separated list of URLs, the cloud followed by its mirrors.
+    pub fn parse_list(text: &str) -> Result<Self, String> {
+        let mut urls = text.split(',').map(str::trim).filter(|u| !u.is_empty());
+        let url = urls.next().ok_or("no cloud URL given")?.to_string();
+        let endpoints = Self {
+            url,
+            mirrors: urls.map(str::to_string).collect(),
+        };
+        endpoints.check()?;
+        Ok(endpoints)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        match self
+            .urls()
+            .find(|u| !(u.starts_with("http://") || u.starts_with("https://")))
+        {
+            Some(url) => Err(format!("invalid cloud URL \"{url}\", expected http:// or https://")),
+            None => Ok(()),
+        }
+    }
+
+    /// The cloud URL, then the mirrors, without trailing slashes.
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(&self.url)
+            .chain(&self.mirrors)
+            .map(|u| u.trim_end_matches('/'))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    sources: Option<Vec<FirmwareSource>>,
+    #[serde(default)]
+    cloud: Option<CloudEndpoints>,
+    #[serde(default)]
+    require_signatures: bool,
+}
+
+/// The settings from the config file.
+pub struct Config {
+    pub sources: Vec<FirmwareSource>,
+    pub cloud: CloudEndpoints,
+    pub require_signatures: bool,
+}
+
+/// The firmware directory followed by the GOcontroll cloud.
+pub fn default_sources() -> Vec<FirmwareSource> {
+    vec![
+        FirmwareSource::LocalDir {
+            path: PathBuf::from(FIRMWARE_DIR),
+        },
+        FirmwareSource::Cloud,
+    ]
+}
+
+/// Read the config file. Without a config file, or without `sources` in it,
+/// the sources are the firmware directory and the cloud. The firmware
+/// directory is added before the first remote source when it is not listed,
+/// so listed local directories take precedence over downloaded files.
+/// Without `cloud` the cloud is `CLOUD_BASE_URL` without mirrors. Signatures are only
+/// required with `require_signatures`.
+pub fn load(path: &str) -> Result<Config, String> {
+    let config: RawConfig = match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("{path}: {e}"))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => RawConfig::default(),
+        Err(e) => return Err(format!("{path}: {e}")),
+    };
+    let cloud = config.cloud.unwrap_or_default();
+    cloud.check().map_err(|e| format!("{path}: {e}"))?;
+    let require_signatures = config.require_signatures;
+    let Some(mut sources) = config.sources else {
+        return Ok(Config {
+            sources: default_sources(),
+            cloud,
+            require_signatures,
+        });
+    };
+    if !sources.iter().any(FirmwareSource::is_firmware_dir) {
+        let first_remote = sources
+            .iter()
+            .position(FirmwareSource::is_remote)
+            .unwrap_or(sources.len());
+        sources.insert(
+            first_remote,
+            FirmwareSource::LocalDir {
+                path: PathBuf::from(FIRMWARE_DIR),
+            },
+        );
+    }
+    Ok(Config {
+        sources,
+        cloud,
+        require_signatures,
+    })
+}
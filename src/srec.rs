@@ -0,0 +1,550 @@
+//! Motorola S-record parsing and validation.
+//!
+//! A firmware file is parsed completely before anything is sent to a module:
+//! once the wipe frame has gone out, a malformed line can no longer be
+//! rejected without leaving the module empty.
+
+use std::fmt::{self, Display};
+
+/// One decoded S-record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Record type, the digit after the `S` (0-9, 4 is reserved).
+    pub kind: u8,
+    pub address: u32,
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// Width of the address field for a record type, None for unknown types.
+    pub fn address_len(kind: u8) -> Option<usize> {
+        match kind {
+            0 | 1 | 5 | 9 => Some(2),
+            2 | 6 | 8 => Some(3),
+            3 | 7 => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Binary form of the record as it appears on the line: byte count,
+    /// address, data and checksum. This is what the bootloader expects in a
+    /// firmware frame.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let address_len = Record::address_len(self.kind).unwrap_or(4);
+        let mut bytes = Vec::with_capacity(address_len + self.data.len() + 2);
+        bytes.push((address_len + self.data.len() + 1) as u8);
+        bytes.extend_from_slice(&self.address.to_be_bytes()[4 - address_len..]);
+        bytes.extend_from_slice(&self.data);
+        bytes.push(checksum(&bytes));
+        bytes
+    }
+}
+
+/// S-record checksum: ones' complement of the low byte of the sum of the
+/// byte count, address and data bytes.
+fn checksum(bytes: &[u8]) -> u8 {
+    !bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The file holds no records at all.
+    Empty,
+    /// The line does not start with `S`.
+    MissingStart,
+    /// Unknown or reserved record type.
+    InvalidType(char),
+    /// Non-hex character or an odd number of hex digits.
+    InvalidHex,
+    /// The byte count does not match the number of bytes on the line.
+    LengthMismatch { declared: usize, actual: usize },
+    /// The line is too short to hold the address of its record type.
+    TooShort,
+    Checksum { expected: u8, found: u8 },
+    /// S0 anywhere but on the first line.
+    HeaderNotFirst,
+    /// An S5/S6/S7/S8/S9 record that carries data bytes.
+    UnexpectedData,
+    /// S5/S6 record count that does not match the data records before it.
+    CountMismatch { declared: u32, actual: u32 },
+    /// Any record after the termination record.
+    AfterTermination,
+    /// No S7/S8/S9 record at the end of the file.
+    MissingTermination,
+}
+
+/// A parse error, `line` is 1-based.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SrecError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+impl Display for SrecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Empty => return write!(f, "file contains no S-records"),
+            ErrorKind::MissingTermination => {
+                return write!(f, "no termination record (S7/S8/S9), file is truncated")
+            }
+            _ => (),
+        }
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::MissingStart => write!(f, "does not start with 'S'"),
+            ErrorKind::InvalidType(c) => write!(f, "invalid record type S{c}"),
+            ErrorKind::InvalidHex => write!(f, "invalid hex data"),
+            ErrorKind::LengthMismatch { declared, actual } => write!(
+                f,
+                "byte count says {declared} bytes but the line holds {actual}"
+            ),
+            ErrorKind::TooShort => write!(f, "record too short for its address field"),
+            ErrorKind::Checksum { expected, found } => write!(
+                f,
+                "checksum mismatch, expected {expected:02X} but found {found:02X}"
+            ),
+            ErrorKind::HeaderNotFirst => write!(f, "S0 header record is not the first record"),
+            ErrorKind::UnexpectedData => write!(f, "count/termination record carries data"),
+            ErrorKind::CountMismatch { declared, actual } => write!(
+                f,
+                "record count says {declared} data records but {actual} were found"
+            ),
+            ErrorKind::AfterTermination => write!(f, "record after the termination record"),
+            ErrorKind::Empty | ErrorKind::MissingTermination => unreachable!(),
+        }
+    }
+}
+
+/// Parse a single line (without line ending) into a record.
+fn parse_line(line: &str, number: usize) -> Result<Record, SrecError> {
+    let err = |kind| SrecError { line: number, kind };
+    let mut chars = line.chars();
+    if chars.next() != Some('S') {
+        return Err(err(ErrorKind::MissingStart));
+    }
+    let kind = match chars.next() {
+        Some(c) => match c.to_digit(10) {
+            Some(d) if d != 4 => d as u8,
+            _ => return Err(err(ErrorKind::InvalidType(c))),
+        },
+        None => return Err(err(ErrorKind::TooShort)),
+    };
+    let bytes = hex::decode(&line[2..]).map_err(|_| err(ErrorKind::InvalidHex))?;
+    let (&count, rest) = bytes.split_first().ok_or(err(ErrorKind::TooShort))?;
+    if count as usize != rest.len() {
+        return Err(err(ErrorKind::LengthMismatch {
+            declared: count as usize,
+            actual: rest.len(),
+        }));
+    }
+    let address_len = Record::address_len(kind).unwrap();
+    if rest.len() < address_len + 1 {
+        return Err(err(ErrorKind::TooShort));
+    }
+    let (body, found) = bytes.split_at(bytes.len() - 1);
+    let expected = checksum(body);
+    if expected != found[0] {
+        return Err(err(ErrorKind::Checksum {
+            expected,
+            found: found[0],
+        }));
+    }
+    let address = rest[..address_len]
+        .iter()
+        .fold(0u32, |acc, b| (acc << 8) | *b as u32);
+    Ok(Record {
+        kind,
+        address,
+        data: rest[address_len..rest.len() - 1].to_vec(),
+    })
+}
+
+/// Parse and validate a complete S-record file.
+///
+/// Accepts `\n` and `\r\n` line endings and ignores blank lines. Every record
+/// is checked for a valid type, hex encoding, byte count and checksum; on top
+/// of that the file as a whole must start with at most one S0 header, have
+/// S5/S6 counts that match, and end with exactly one S7/S8/S9 record.
+pub fn parse(text: &str) -> Result<Vec<Record>, SrecError> {
+    let mut records: Vec<Record> = Vec::new();
+    let mut data_records: u32 = 0;
+    let mut terminated = false;
+
+    for (i, raw) in text.split('\n').enumerate() {
+        let number = i + 1;
+        let line = raw.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        let record = parse_line(line, number)?;
+        let err = |kind| Err(SrecError { line: number, kind });
+
+        if terminated {
+            return err(ErrorKind::AfterTermination);
+        }
+        match record.kind {
+            0 if !records.is_empty() => return err(ErrorKind::HeaderNotFirst),
+            1..=3 => data_records += 1,
+            5 | 6 => {
+                if !record.data.is_empty() {
+                    return err(ErrorKind::UnexpectedData);
+                }
+                if record.address != data_records {
+                    return err(ErrorKind::CountMismatch {
+                        declared: record.address,
+                        actual: data_records,
+                    });
+                }
+            }
+            7..=9 => {
+                if !record.data.is_empty() {
+                    return err(ErrorKind::UnexpectedData);
+                }
+                terminated = true;
+            }
+            _ => (),
+        }
+        records.push(record);
+    }
+
+    if records.is_empty() {
+        return Err(SrecError {
+            line: 0,
+            kind: ErrorKind::Empty,
+        });
+    }
+    if !terminated {
+        return Err(SrecError {
+            line: text.lines().count(),
+            kind: ErrorKind::MissingTermination,
+        });
+    }
+    Ok(records)
+}
+
+/// Smallest data record type whose address field can hold `address`.
+fn data_kind_for(address: u64) -> u8 {
+    match address {
+        0..=0xFFFF => 1,
+        0x1_0000..=0xFF_FFFF => 2,
+        _ => 3,
+    }
+}
+
+/// Split records so none is longer than `max_len` bytes in binary form (byte
+/// count, address, data and checksum), so files built with any record width
+/// can be sent one record per frame.
+///
+/// S1/S2/S3 records are cut into consecutive chunks with the address moved
+/// along; a chunk whose address no longer fits the original address width is
+/// promoted to S2/S3. S5/S6 counts are recomputed for the new number of data
+/// records. The S0 header is kept but its data is shortened when it does not
+/// fit, it only carries a description. Termination records always fit.
+///
+/// Fails when a data record runs past the end of the 32-bit address space,
+/// its chunks would otherwise wrap around to address 0.
+pub fn reblock(records: Vec<Record>, max_len: usize) -> Result<Vec<Record>, String> {
+    let mut out = Vec::with_capacity(records.len());
+    let mut data_records: u32 = 0;
+    for mut record in records {
+        match record.kind {
+            0 => {
+                record.data.truncate(max_len - 4);
+                out.push(record);
+            }
+            1..=3 if record.data.is_empty() => {
+                data_records += 1;
+                out.push(record);
+            }
+            1..=3 => {
+                let end = record.address as u64 + record.data.len() as u64 - 1;
+                if end > u32::MAX as u64 {
+                    return Err(format!(
+                        "data at {:08X} runs past the end of the address space",
+                        record.address
+                    ));
+                }
+                let kind = record.kind.max(data_kind_for(end));
+                let chunk_len = max_len - 2 - Record::address_len(kind).unwrap();
+                for (i, chunk) in record.data.chunks(chunk_len).enumerate() {
+                    data_records += 1;
+                    out.push(Record {
+                        kind,
+                        address: record.address + (i * chunk_len) as u32,
+                        data: chunk.to_vec(),
+                    });
+                }
+            }
+            5 | 6 => out.push(Record {
+                kind: if data_records <= 0xFFFF { 5 } else { 6 },
+                address: data_records,
+                data: Vec::new(),
+            }),
+            _ => out.push(record),
+        }
+    }
+    Ok(out)
+}
+
+/// Turn a raw binary image loaded at `base` into S3 data records followed by
+/// an S7 record with `base` as start address. The records are not sized for
+/// a frame, run them through `reblock`.
+pub fn from_binary(data: &[u8], base: u32) -> Vec<Record> {
+    let mut records: Vec<Record> = data
+        .chunks(0xFF - 5)
+        .enumerate()
+        .map(|(i, chunk)| Record {
+            kind: 3,
+            address: base.wrapping_add((i * (0xFF - 5)) as u32),
+            data: chunk.to_vec(),
+        })
+        .collect();
+    records.push(Record {
+        kind: 7,
+        address: base,
+        data: Vec::new(),
+    });
+    records
+}
+
+/// Text of the S0 header record when the file starts with one. Only the first
+/// record is looked at, the rest of the file is not validated.
+pub fn header_text(text: &str) -> Option<String> {
+    let (i, line) = text
+        .split('\n')
+        .enumerate()
+        .find(|(_, l)| !l.trim_end().is_empty())?;
+    let record = parse_line(line.trim_end(), i + 1).ok()?;
+    if record.kind != 0 {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&record.data).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example file from the S-record format description.
+    const EXAMPLE: &[&str] = &[
+        "S00F000068656C6C6F202020202000003C",
+        "S11F00007C0802A6900100049421FFF07C6C1B787C8C23783C6000003863000026",
+        "S11F001C4BFFFFE5398000007D83637880010014382100107C0803A64E800020E9",
+        "S111003848656C6C6F20776F726C642E0A0042",
+        "S5030003F9",
+        "S9030000FC",
+    ];
+
+    fn line(kind: u8, address: u32, data: &[u8]) -> String {
+        let record = Record {
+            kind,
+            address,
+            data: data.to_vec(),
+        };
+        format!("S{kind}{}", hex::encode_upper(record.to_bytes()))
+    }
+
+    fn error_kind(text: &str) -> ErrorKind {
+        parse(text).unwrap_err().kind
+    }
+
+    #[test]
+    fn parses_example() {
+        let records = parse(&EXAMPLE.join("\n")).unwrap();
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[0].data, b"hello     \0\0");
+        assert_eq!(records[3].address, 0x38);
+        assert_eq!(records[3].data, b"Hello world.\n\0");
+        assert_eq!(records[5].kind, 9);
+    }
+
+    #[test]
+    fn to_bytes_matches_line() {
+        for text in EXAMPLE {
+            let record = parse_line(text, 1).unwrap();
+            assert_eq!(hex::encode_upper(record.to_bytes()), text[2..]);
+        }
+    }
+
+    #[test]
+    fn accepts_crlf_and_blank_lines() {
+        let lf = parse(&EXAMPLE.join("\n")).unwrap();
+        let crlf = parse(&format!("{}\r\n\r\n", EXAMPLE.join("\r\n"))).unwrap();
+        assert_eq!(lf, crlf);
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut lines: Vec<String> = EXAMPLE.iter().map(|l| l.to_string()).collect();
+        lines[3] = lines[3].replace("0A0042", "0A0043");
+        let err = parse(&lines.join("\n")).unwrap_err();
+        assert_eq!(err.line, 4);
+        assert_eq!(
+            err.kind,
+            ErrorKind::Checksum {
+                expected: 0x42,
+                found: 0x43
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_record() {
+        let mut lines: Vec<String> = EXAMPLE.iter().map(|l| l.to_string()).collect();
+        lines[1].truncate(40);
+        assert_eq!(
+            error_kind(&lines.join("\n")),
+            ErrorKind::LengthMismatch {
+                declared: 0x1F,
+                actual: 18
+            }
+        );
+        lines[1].truncate(39);
+        assert_eq!(error_kind(&lines.join("\n")), ErrorKind::InvalidHex);
+    }
+
+    #[test]
+    fn rejects_missing_termination() {
+        let err = parse(&EXAMPLE[..5].join("\n")).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::MissingTermination);
+        assert_eq!(err.line, 5);
+        for kind in [7, 8, 9] {
+            let text = [line(1, 0, &[1, 2]), line(kind, 0, &[])].join("\n");
+            assert!(parse(&text).is_ok(), "S{kind} terminates a file");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_structure() {
+        assert_eq!(error_kind(""), ErrorKind::Empty);
+        assert_eq!(error_kind("\r\n\n"), ErrorKind::Empty);
+        assert_eq!(error_kind("X1030000FC"), ErrorKind::MissingStart);
+        assert_eq!(error_kind("S4030000FC"), ErrorKind::InvalidType('4'));
+        assert_eq!(error_kind("SA030000FC"), ErrorKind::InvalidType('A'));
+        assert_eq!(error_kind("S3020000"), ErrorKind::TooShort);
+
+        let header_late = [line(1, 0, &[1]), line(0, 0, b"x"), line(9, 0, &[])];
+        assert_eq!(error_kind(&header_late.join("\n")), ErrorKind::HeaderNotFirst);
+        let after_end = [line(1, 0, &[1]), line(9, 0, &[]), line(1, 1, &[2])];
+        assert_eq!(error_kind(&after_end.join("\n")), ErrorKind::AfterTermination);
+        let count = [line(1, 0, &[1]), line(5, 2, &[]), line(9, 0, &[])];
+        assert_eq!(
+            error_kind(&count.join("\n")),
+            ErrorKind::CountMismatch {
+                declared: 2,
+                actual: 1
+            }
+        );
+        let data_in_end = [line(1, 0, &[1]), line(9, 0, &[1])];
+        assert_eq!(error_kind(&data_in_end.join("\n")), ErrorKind::UnexpectedData);
+    }
+
+    #[test]
+    fn reads_header_text() {
+        assert_eq!(
+            header_text(&format!("\r\n{}", EXAMPLE.join("\r\n"))).as_deref(),
+            Some("hello     \0\0")
+        );
+        assert_eq!(header_text(&EXAMPLE[1..].join("\n")), None);
+    }
+
+    fn data_of(records: &[Record]) -> Vec<u8> {
+        records
+            .iter()
+            .filter(|r| (1..=3).contains(&r.kind))
+            .flat_map(|r| r.data.clone())
+            .collect()
+    }
+
+    fn assert_fits(records: &[Record]) {
+        for record in records {
+            assert!(record.to_bytes().len() <= crate::FRAME_RECORD_MAX, "{record:?}");
+        }
+    }
+
+    #[test]
+    fn reblock_splits_at_frame_size() {
+        let data: Vec<u8> = (0..100).collect();
+        let text = [
+            line(0, 0, &[b'h'; 60]),
+            line(1, 0x1000, &data),
+            line(5, 1, &[]),
+            line(9, 0x1000, &[]),
+        ]
+        .join("\n");
+        let records = reblock(parse(&text).unwrap(), crate::FRAME_RECORD_MAX).unwrap();
+        assert_fits(&records);
+        assert_eq!(data_of(&records), data);
+        // 36 byte frames leave 32 data bytes per S1 record
+        let chunks: Vec<&Record> = records.iter().filter(|r| r.kind == 1).collect();
+        assert_eq!(chunks.len(), 4);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.address, 0x1000 + 32 * i as u32);
+        }
+        assert_eq!(records.first().unwrap().kind, 0);
+        let count = &records[records.len() - 2];
+        assert_eq!((count.kind, count.address), (5, 4));
+        assert_eq!(records.last().unwrap().kind, 9);
+        // the result is a valid file again
+        let lines: Vec<String> = records.iter().map(|r| line(r.kind, r.address, &r.data)).collect();
+        assert_eq!(parse(&lines.join("\n")).unwrap(), records);
+    }
+
+    #[test]
+    fn reblock_promotes_address_width() {
+        let data: Vec<u8> = (0..40).collect();
+        let records = reblock(
+            vec![
+                Record { kind: 1, address: 0xFFF0, data: data.clone() },
+                Record { kind: 2, address: 0xFF_FFF0, data: data.clone() },
+                Record { kind: 9, address: 0, data: Vec::new() },
+            ],
+            crate::FRAME_RECORD_MAX,
+        )
+        .unwrap();
+        assert_fits(&records);
+        let kinds: Vec<(u8, u32)> = records.iter().map(|r| (r.kind, r.address)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (2, 0xFFF0),
+                (2, 0xFFF0 + 31),
+                (3, 0xFF_FFF0),
+                (3, 0xFF_FFF0 + 30),
+                (9, 0),
+            ]
+        );
+        assert_eq!(data_of(&records), [data.clone(), data].concat());
+    }
+
+    #[test]
+    fn reblock_rejects_address_overflow() {
+        let records = vec![
+            Record { kind: 3, address: 0xFFFF_FFF0, data: vec![0; 16] },
+            Record { kind: 7, address: 0, data: Vec::new() },
+        ];
+        assert!(reblock(records.clone(), crate::FRAME_RECORD_MAX).is_ok());
+        let mut records = records;
+        records[0].data.push(0);
+        assert!(reblock(records, crate::FRAME_RECORD_MAX).is_err());
+        assert!(reblock(from_binary(&[0; 300], 0xFFFF_FF00), crate::FRAME_RECORD_MAX).is_err());
+    }
+
+    #[test]
+    fn reblock_keeps_fitting_records() {
+        let text = [EXAMPLE[0], EXAMPLE[3], "S5030001FB", EXAMPLE[5]];
+        let records = parse(&text.join("\n")).unwrap();
+        assert_eq!(reblock(records.clone(), crate::FRAME_RECORD_MAX).unwrap(), records);
+    }
+
+    #[test]
+    fn binary_image_becomes_records() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let records = reblock(from_binary(&data, 0x0800_4000), crate::FRAME_RECORD_MAX).unwrap();
+        assert_fits(&records);
+        assert_eq!(data_of(&records), data);
+        assert_eq!(records[0].address, 0x0800_4000);
+        let last = records.last().unwrap();
+        assert_eq!((last.kind, last.address), (7, 0x0800_4000));
+    }
+}